@@ -1,19 +1,89 @@
-use miette::Result;
+use chrono::{DateTime, Duration, Utc};
 use zellij_tile::prelude::*;
 use zjk8s::{
-    kubernetes::{self, ListDir},
-    render::{ColType, Render},
+    cache::{Cache, DEFAULT_CACHE_TTL},
+    command::{self, Command, Completions},
+    config::{parse_bool, parse_number},
+    custom::{custom_actions, CustomAction, CustomOutput, Placeholders},
+    errors::Errors,
+    favorites::{Favorite, Favorites},
+    history::{History, Location},
+    keymap::{Action, Keymap},
+    kubernetes::{self, ApplyStrategy, DetailsKind, ListDir, ResourceRef, Selection},
+    loading::REFRESH_INTERVAL,
+    prompt::{Prompt, PromptAction, PromptResult},
+    render::{self, ColType, Render, View},
+    safety::{MutatingAction, Safety},
+    scheduler::{Query, Scheduler},
+    session::{self, ViewState},
 };
 
-use std::collections::BTreeMap;
+use std::{collections::BTreeMap, path::PathBuf};
+
+// columns the resource details are scrolled horizontally per key press
+const HORIZONTAL_SCROLL_STEP: isize = 8;
+
+// options suggested, when draining a node
+const DEFAULT_DRAIN_OPTIONS: &str = "--ignore-daemonsets --delete-emptydir-data";
+
+// rows of the error area including its title
+const MAX_ERROR_ROWS: usize = 6;
+
+// milliseconds the selection must rest on a resource, before its details are
+// previewed
+const DEFAULT_PREVIEW_DELAY: u64 = 300;
 
 #[derive(Default)]
 struct State {
     userspace_configuration: BTreeMap<String, String>,
     cluster_state: kubernetes::State,
     selected_col: ColType,
-    error_message: Option<Result<()>>,
+    errors: Errors,
     renderer: Render,
+    view: View,
+    show_help: bool,
+    prompt: Option<Prompt>,
+    notice: Option<String>,
+    safety: Safety,
+    keymap: Keymap,
+    custom_actions: Vec<CustomAction>,
+    history: History,
+
+    // name of the plugin instance, used to save and restore its view state
+    instance: String,
+
+    // folder in the host folder for the favorites and view states
+    state_dir: PathBuf,
+    saved_view_state: Option<ViewState>,
+
+    // rows of the last render, used to compute the page size
+    rows: usize,
+
+    scheduler: Scheduler,
+
+    // time of the next refresh of the metrics and nodes
+    next_refresh: DateTime<Utc>,
+
+    // seconds until the details of the selected resource are previewed,
+    // None when the preview is disabled
+    preview_delay: Option<f64>,
+
+    // resource waiting to be previewed with the time it is due and the
+    // resource, whose preview was queried last
+    preview: Option<(ResourceRef, DateTime<Utc>)>,
+    previewed: Option<ResourceRef>,
+
+    // column below the mouse cursor, that is scrolled with the mouse wheel
+    hovered_col: Option<ColType>,
+
+    apply_strategy: ApplyStrategy,
+
+    // resource, that is edited as soon as its details are loaded
+    edit_requested: Option<ResourceRef>,
+
+    // files in the host folder, that are currently opened in an editor
+    // pane, with their original content
+    edited_files: BTreeMap<String, String>,
 }
 
 register_plugin!(State);
@@ -24,10 +94,160 @@ impl ZellijPlugin for State {
 
         request_permission(&[
             PermissionType::ReadApplicationState,
+            PermissionType::ChangeApplicationState,
             PermissionType::RunCommands,
         ]);
 
-        subscribe(&[EventType::Key, EventType::RunCommandResult]);
+        subscribe(&[
+            EventType::Key,
+            EventType::Mouse,
+            EventType::RunCommandResult,
+            EventType::CommandPaneOpened,
+            EventType::CommandPaneExited,
+            EventType::Timer,
+            EventType::PermissionRequestResult,
+        ]);
+
+        self.safety = match Safety::new(
+            self.userspace_configuration
+                .get("read_only")
+                .map(|s| s.as_str()),
+            self.userspace_configuration
+                .get("protected_contexts")
+                .map(|s| s.as_str()),
+        ) {
+            Ok(s) => s,
+            Err(e) => {
+                self.errors.push(e, None);
+                Safety::default()
+            }
+        };
+
+        self.keymap = match Keymap::new(&self.userspace_configuration) {
+            Ok(k) => k,
+            Err(e) => {
+                self.errors.push(e, None);
+                Keymap::new(&BTreeMap::new()).unwrap_or_default()
+            }
+        };
+
+        self.custom_actions = match custom_actions(&self.userspace_configuration, &self.keymap) {
+            Ok(a) => a,
+            Err(e) => {
+                self.errors.push(e, None);
+                vec![]
+            }
+        };
+
+        self.cluster_state.wrap_resource_details = match parse_bool(
+            "wrap_details",
+            self.userspace_configuration
+                .get("wrap_details")
+                .map(|s| s.as_str()),
+            false,
+        ) {
+            Ok(w) => w,
+            Err(e) => {
+                self.errors.push(e, None);
+                false
+            }
+        };
+
+        let preview = match parse_bool(
+            "preview_details",
+            self.userspace_configuration
+                .get("preview_details")
+                .map(|s| s.as_str()),
+            false,
+        ) {
+            Ok(p) => p,
+            Err(e) => {
+                self.errors.push(e, None);
+                false
+            }
+        };
+
+        let preview_delay = match parse_number(
+            "preview_delay",
+            self.userspace_configuration
+                .get("preview_delay")
+                .map(|s| s.as_str()),
+            DEFAULT_PREVIEW_DELAY,
+        ) {
+            Ok(d) => d,
+            Err(e) => {
+                self.errors.push(e, None);
+                DEFAULT_PREVIEW_DELAY
+            }
+        };
+
+        self.preview_delay = preview.then_some(preview_delay as f64 / 1000.0);
+
+        let cache_ttl = match parse_number(
+            "cache_ttl",
+            self.userspace_configuration
+                .get("cache_ttl")
+                .map(|s| s.as_str()),
+            DEFAULT_CACHE_TTL,
+        ) {
+            Ok(t) => t,
+            Err(e) => {
+                self.errors.push(e, None);
+                DEFAULT_CACHE_TTL
+            }
+        };
+        self.cluster_state.cache = Cache::new(cache_ttl);
+
+        self.cluster_state.wrap_around = match parse_bool(
+            "wrap_around",
+            self.userspace_configuration
+                .get("wrap_around")
+                .map(|s| s.as_str()),
+            true,
+        ) {
+            Ok(w) => w,
+            Err(e) => {
+                self.errors.push(e, None);
+                true
+            }
+        };
+
+        self.state_dir = session::state_dir(
+            self.userspace_configuration
+                .get("state_dir")
+                .map(|s| s.as_str()),
+        );
+
+        self.cluster_state.favorites = match Favorites::load(&self.state_dir) {
+            Ok(f) => f,
+            Err(e) => {
+                self.notice = Some(e);
+                Favorites::new(&self.state_dir)
+            }
+        };
+
+        self.instance = self
+            .userspace_configuration
+            .get("instance")
+            .cloned()
+            .unwrap_or_else(|| "default".to_owned());
+        self.restore_view_state();
+
+        kubernetes::query_context_info(&self.kubernetes_context());
+        self.schedule_queries();
+        self.schedule_refresh();
+
+        self.apply_strategy = match ApplyStrategy::new(
+            self.userspace_configuration
+                .get("edit_apply_strategy")
+                .map(|s| s.as_str()),
+        ) {
+            Ok(s) => s,
+            Err(e) => {
+                self.errors.push(e, None);
+                ApplyStrategy::default()
+            }
+        };
 
         self.renderer = match Render::new(
             self.userspace_configuration
@@ -39,10 +259,13 @@ impl ZellijPlugin for State {
             self.userspace_configuration
                 .get("selected_col_selected_item_bg")
                 .map(|s| s.as_str()),
+            self.userspace_configuration
+                .get("context_colors")
+                .map(|s| s.as_str()),
         ) {
             Ok(r) => r,
             Err(e) => {
-                self.error_message = Some(Err(e));
+                self.errors.push(e, None);
                 Render::default()
             }
         };
@@ -52,15 +275,255 @@ impl ZellijPlugin for State {
         let mut should_render = false;
         match event {
             Event::RunCommandResult(exit_code, stdout, stderr, context) => {
-                self.error_message = Some(
-                    self.cluster_state
-                        .parse_result(exit_code, stdout, stderr, context),
-                );
+                // results of superseded queries are dropped
+                if !self.scheduler.finish(&context) {
+                    return false;
+                }
+
+                let command = context.get("command").cloned().unwrap_or_default();
+                let path = context.get("path").cloned();
+                let file = context.get("file").cloned();
+                let metrics_available = !self.cluster_state.metrics_unavailable;
+
+                match self
+                    .cluster_state
+                    .parse_result(exit_code, stdout, stderr, context.clone())
+                {
+                    Ok(()) => {
+                        self.errors.resolve(&command);
+                        self.cluster_state.loading.finish(&command);
+                    }
+                    Err(e) => {
+                        self.errors.push(e, Some(context));
+                        self.cluster_state.query_failed(&command);
+                        if command == "query_resource_details" {
+                            self.edit_requested = None;
+                        }
+
+                        return true;
+                    }
+                }
+
+                match (command.as_str(), path, file) {
+                    ("query_resource_details", _, _) if self.edit_requested.is_some() => {
+                        if self.edit_requested.take() == self.cluster_state.resource_details_ref {
+                            self.edit_resource();
+                        }
+                    }
+                    // kubectl diff exits with 1, when the resource differs.
+                    // The changes are confirmed with the diff on screen.
+                    ("diff_resource", Some(path), Some(file)) => match exit_code {
+                        Some(0) => {
+                            self.notice =
+                                Some("No differences to the cluster, nothing applied".to_owned());
+                            remove_host_file(&file);
+                        }
+                        Some(1) => self.run_mutating_action(MutatingAction::Apply { path, file }),
+                        _ => remove_host_file(&file),
+                    },
+                    ("apply_resource", _, Some(file)) => remove_host_file(&file),
+                    ("query_ports", _, _) => self.prompt_port_forward(),
+                    ("query_resources", _, _) => self.refresh_metrics(),
+                    ("node_action", _, _) => {
+                        self.notice = self.cluster_state.node_action_output.take();
+                        self.refresh_nodes();
+                    }
+                    // the notice is shown, when the metrics become unavailable
+                    ("query_metrics" | "query_node_metrics", _, _)
+                        if metrics_available && self.cluster_state.metrics_unavailable =>
+                    {
+                        self.notice = Some(
+                            "Metrics are not available, is the metrics-server installed?"
+                                .to_owned(),
+                        );
+                    }
+                    _ => (),
+                }
+
+                should_render = true;
+            }
+            Event::PermissionRequestResult(PermissionStatus::Granted) => {
+                // commands run before the permissions were granted are lost,
+                // so the queries in flight are dispatched again
+                self.scheduler.clear();
+                kubernetes::query_context_info(&self.kubernetes_context());
+
+                should_render = true;
+            }
+            // timers are also set for debounced queries, which are
+            // dispatched below
+            Event::Timer(_) if Utc::now() >= self.next_refresh => {
+                self.refresh_metrics();
+                if self.view == View::Nodes {
+                    self.refresh_nodes();
+                }
+                self.schedule_refresh();
+
+                // update the stale markers
+                should_render = true;
+            }
+            Event::CommandPaneOpened(pane_id, context) => {
+                if context.get("command").map(|c| c.as_str()) == Some("port_forward") {
+                    self.cluster_state.port_forward_opened(pane_id, &context);
+
+                    should_render = true;
+                }
+            }
+            Event::CommandPaneExited(pane_id, exit_code, context) => {
+                match context.get("command").map(|c| c.as_str()) {
+                    Some("edit_resource") => {
+                        close_terminal_pane(pane_id);
+
+                        if let (Some(path), Some(file)) = (context.get("path"), context.get("file"))
+                        {
+                            self.apply_edited_file(path, file);
+                        }
+
+                        should_render = true;
+                    }
+                    Some("port_forward") => {
+                        self.cluster_state.port_forward_exited(exit_code, &context);
+
+                        should_render = true;
+                    }
+                    _ => (),
+                }
+            }
+            Event::Mouse(mouse) if self.view == View::Resources && self.prompt.is_none() => {
+                should_render = self.handle_mouse(mouse);
+            }
+            Event::Key(_) if self.notice.is_some() && self.prompt.is_none() => {
+                self.notice = None;
 
                 should_render = true;
             }
-            Event::Key(key) => match key.bare_key {
-                BareKey::Left => {
+            Event::Key(key) if self.prompt.is_some() => {
+                self.handle_prompt_key(&key);
+
+                should_render = true;
+            }
+            Event::Key(key)
+                if !self.errors.is_empty()
+                    && matches!(
+                        self.keymap.action(&key),
+                        Some(Action::Retry | Action::DismissError)
+                    ) =>
+            {
+                if self.keymap.action(&key) == Some(Action::Retry) {
+                    self.retry();
+                } else {
+                    self.errors.dismiss();
+                }
+
+                should_render = true;
+            }
+            Event::Key(_) if self.show_help => {
+                self.show_help = false;
+
+                should_render = true;
+            }
+            Event::Key(key) if self.keymap.action(&key) == Some(Action::Help) => {
+                self.show_help = true;
+
+                should_render = true;
+            }
+            Event::Key(key) if self.view == View::PortForwards => match self.keymap.action(&key) {
+                Some(action) if self.list_dir(action).is_some() => {
+                    if let Some(direction) = self.list_dir(action) {
+                        self.cluster_state.select_port_forward(direction);
+                    }
+
+                    should_render = true;
+                }
+                Some(Action::StopPortForward) => {
+                    self.cluster_state.stop_selected_port_forward();
+
+                    should_render = true;
+                }
+                Some(Action::PortForwards) | Some(Action::Quit) => {
+                    self.view = View::Resources;
+
+                    should_render = true;
+                }
+                _ => (),
+            },
+            Event::Key(key) if self.view == View::Nodes => match self.keymap.action(&key) {
+                Some(action) if self.list_dir(action).is_some() => {
+                    if let Some(direction) = self.list_dir(action) {
+                        self.cluster_state.select_node(direction);
+                    }
+
+                    should_render = true;
+                }
+                Some(Action::Refresh) => {
+                    self.refresh_nodes();
+                }
+                Some(action @ (Action::Cordon | Action::Uncordon)) => {
+                    if let Some(node) = self.cluster_state.get_selected_node() {
+                        let node = node.name.clone();
+
+                        self.run_mutating_action(match action {
+                            Action::Cordon => MutatingAction::Cordon { node },
+                            _ => MutatingAction::Uncordon { node },
+                        });
+                    }
+
+                    should_render = true;
+                }
+                Some(Action::Drain) => {
+                    if let Some(node) = self.cluster_state.get_selected_node() {
+                        self.prompt = Some(Prompt::new(
+                            &format!("Drain options for {}", node.name),
+                            DEFAULT_DRAIN_OPTIONS,
+                            PromptAction::DrainOptions(node.name.clone()),
+                        ));
+                    }
+
+                    should_render = true;
+                }
+                Some(Action::Nodes) | Some(Action::Quit) => {
+                    self.view = View::Resources;
+
+                    should_render = true;
+                }
+                _ => (),
+            },
+            Event::Key(key) if self.view == View::Favorites => match self.keymap.action(&key) {
+                Some(action) if self.list_dir(action).is_some() => {
+                    if let Some(direction) = self.list_dir(action) {
+                        self.cluster_state.select_favorite(direction);
+                    }
+
+                    should_render = true;
+                }
+                Some(Action::Select) => {
+                    self.open_favorite();
+
+                    should_render = true;
+                }
+                Some(Action::Favorite) => {
+                    if let Err(e) = self.cluster_state.remove_selected_favorite() {
+                        self.notice = Some(e);
+                    }
+
+                    should_render = true;
+                }
+                Some(Action::Favorites) | Some(Action::Quit) => {
+                    self.view = View::Resources;
+
+                    should_render = true;
+                }
+                _ => (),
+            },
+            Event::Key(key) if self.custom_action(&key).is_some() => {
+                if let Some(action) = self.custom_action(&key) {
+                    self.run_custom_action(action);
+                }
+
+                should_render = true;
+            }
+            Event::Key(key) => match self.keymap.action(&key) {
+                Some(Action::Left) => {
                     self.selected_col = match self.selected_col {
                         ColType::Namespace => ColType::Namespace,
                         ColType::ResourceType => ColType::Namespace,
@@ -70,7 +533,7 @@ impl ZellijPlugin for State {
 
                     should_render = true;
                 }
-                BareKey::Right => {
+                Some(Action::Right) => {
                     self.selected_col = match self.selected_col {
                         ColType::Namespace => ColType::ResourceType,
                         ColType::ResourceType => ColType::Resource,
@@ -80,107 +543,1377 @@ impl ZellijPlugin for State {
 
                     should_render = true;
                 }
-                BareKey::Up => {
+                Some(action) if self.list_dir(action).is_some() => {
+                    if let Some(direction) = self.list_dir(action) {
+                        self.cluster_state
+                            .select_item(direction, &self.selected_col);
+                    }
+
+                    should_render = true;
+                }
+                Some(Action::ScrollLeft) if self.selected_col == ColType::ResourceDetails => {
                     self.cluster_state
-                        .select_item(ListDir::Up, &self.selected_col);
+                        .scroll_resource_details(-HORIZONTAL_SCROLL_STEP);
 
                     should_render = true;
                 }
-                BareKey::Down => {
+                Some(Action::ScrollRight) if self.selected_col == ColType::ResourceDetails => {
                     self.cluster_state
-                        .select_item(ListDir::Down, &self.selected_col);
+                        .scroll_resource_details(HORIZONTAL_SCROLL_STEP);
 
                     should_render = true;
                 }
-                BareKey::Enter => {
-                    if self.selected_col == ColType::Resource {
-                        let namespace = self.cluster_state.get_selected_item(&ColType::Namespace);
-                        let resource_type =
-                            self.cluster_state.get_selected_item(&ColType::ResourceType);
-                        let resource = self.cluster_state.get_selected_item(&ColType::Resource);
+                Some(Action::ToggleWrap) if self.selected_col == ColType::ResourceDetails => {
+                    self.cluster_state.toggle_wrap_resource_details();
 
-                        if namespace.is_some() && resource_type.is_some() && resource.is_some() {
-                            self.cluster_state.refresh_resource_details = true;
-                            should_render = true;
+                    should_render = true;
+                }
+                Some(Action::Sort) if self.selected_col == ColType::Resource => {
+                    self.cluster_state.cycle_resource_sort();
 
-                            kubernetes::query_resource_details(
-                                self.userspace_configuration
-                                    .get("kubernetes_context")
-                                    .map(|s| s.as_str()),
-                                &namespace.unwrap(),
-                                &resource_type.unwrap(),
-                                &resource.unwrap(),
-                            );
+                    should_render = true;
+                }
+                Some(Action::ReverseSort) if self.selected_col == ColType::Resource => {
+                    self.cluster_state.reverse_resource_sort();
 
-                            self.selected_col = ColType::ResourceDetails;
-                        }
-                    }
+                    should_render = true;
                 }
-                BareKey::Esc => {
-                    close_self();
+                Some(Action::Tree) if self.selected_col == ColType::Resource => {
+                    if let Some(resource_ref) = self.selected_resource_ref() {
+                        let request_id = self.details_request();
+                        kubernetes::query_owner_tree(
+                            &self.kubernetes_context(),
+                            &resource_ref,
+                            request_id,
+                        );
+                        self.cluster_state.loading.start("query_owner_tree");
+                        self.selected_col = ColType::ResourceDetails;
+                    }
+
+                    should_render = true;
                 }
-                _ => (),
-            },
-            _ => (),
-        };
-        should_render
-    }
+                Some(Action::Related) if self.selected_col == ColType::Resource => {
+                    if let Some(resource_ref) = self.selected_resource_ref() {
+                        let request_id = self.details_request();
+                        kubernetes::query_related(
+                            &self.kubernetes_context(),
+                            &resource_ref,
+                            request_id,
+                        );
+                        self.cluster_state.loading.start("query_related");
+                        self.selected_col = ColType::ResourceDetails;
+                    }
 
-    fn render(&mut self, rows: usize, cols: usize) {
-        if let Some(Err(e)) = &self.error_message {
-            println!("Error: {:?}", e);
+                    should_render = true;
+                }
+                Some(Action::Select) if self.selected_col == ColType::ResourceDetails => {
+                    // the details must belong to the selected resource, so
+                    // kinds, that are not listed in the columns, are skipped
+                    match self.cluster_state.selected_related_resource().cloned() {
+                        Some(resource_ref) if self.cluster_state.can_select(&resource_ref) => {
+                            self.visit_details(&resource_ref);
+                            self.cluster_state.select(Selection::from(&resource_ref));
+                            self.query_resource_details(&resource_ref);
+                        }
+                        Some(resource_ref) => {
+                            self.notice = Some(format!(
+                                "{}/{} is not listed in the columns",
+                                resource_ref.resource_type, resource_ref.name
+                            ));
+                        }
+                        None => (),
+                    }
 
-            return;
-        }
+                    should_render = true;
+                }
+                Some(Action::JumpToLine) => {
+                    self.prompt = Some(Prompt::new("Jump to line", "", PromptAction::JumpToLine));
 
-        let k8s_context = self
-            .userspace_configuration
-            .get("kubernetes_context")
-            .map(|s| s.as_str());
+                    should_render = true;
+                }
+                Some(Action::Command) => {
+                    if self.cluster_state.contexts.is_none() {
+                        kubernetes::query_contexts();
+                    }
+                    if self.cluster_state.resource_aliases.is_none() {
+                        kubernetes::query_api_resources(&self.kubernetes_context());
+                    }
 
-        if self.cluster_state.namespaces.is_none() {
-            eprintln!("Querying namespaces...");
-            kubernetes::query_namespaces(k8s_context);
-        }
+                    self.prompt = Some(Prompt::new("", "", PromptAction::Command));
 
-        if self.cluster_state.refresh_resource_types {
-            eprintln!("Querying resource types...");
+                    should_render = true;
+                }
+                Some(Action::Select) if self.selected_col == ColType::Resource => {
+                    if let Some(resource_ref) = self.selected_resource_ref() {
+                        self.visit_details(&resource_ref);
+                        self.cluster_state.refresh_resource_details = true;
+                        self.query_resource_details(&resource_ref);
 
-            self.refresh_resource_types(&k8s_context);
-        }
+                        self.selected_col = ColType::ResourceDetails;
+                    }
 
-        if self.cluster_state.refresh_resources {
-            eprintln!("Querying resources...");
+                    should_render = true;
+                }
+                Some(Action::Back) => {
+                    if let Some(location) = self.history.back(self.location()) {
+                        self.restore(location);
+                    }
 
-            self.refresh_resources(&k8s_context);
-        }
+                    should_render = true;
+                }
+                Some(Action::Forward) => {
+                    if let Some(location) = self.history.forward(self.location()) {
+                        self.restore(location);
+                    }
 
-        self.renderer
-            .render_cluster_state(&self.cluster_state, &self.selected_col, rows, cols)
-    }
-}
+                    should_render = true;
+                }
+                Some(Action::Edit) => {
+                    self.request_edit();
 
-impl State {
-    fn refresh_resource_types(&self, k8s_context: &Option<&str>) {
-        let namespace = match self.cluster_state.get_selected_item(&ColType::Namespace) {
-            Some(namespace) => namespace,
-            None => return,
-        };
+                    should_render = true;
+                }
+                Some(Action::PortForward) => {
+                    self.request_port_forward();
 
-        kubernetes::query_resource_types(k8s_context, &namespace);
-    }
+                    should_render = true;
+                }
+                Some(Action::PortForwards) => {
+                    self.view = View::PortForwards;
 
-    fn refresh_resources(&self, k8s_context: &Option<&str>) {
-        let namespace = match self.cluster_state.get_selected_item(&ColType::Namespace) {
-            Some(namespace) => namespace,
-            None => return,
+                    should_render = true;
+                }
+                Some(Action::Favorite) => {
+                    if let Err(e) = self.cluster_state.toggle_favorite(&self.selected_col) {
+                        self.notice = Some(e);
+                    }
+
+                    should_render = true;
+                }
+                Some(Action::Refresh) => {
+                    self.force_refresh();
+
+                    should_render = true;
+                }
+                Some(Action::Favorites) => {
+                    if self.cluster_state.selected_favorite.is_none()
+                        && !self.cluster_state.favorites.items().is_empty()
+                    {
+                        self.cluster_state.selected_favorite = Some(0);
+                    }
+                    self.view = View::Favorites;
+
+                    should_render = true;
+                }
+                Some(Action::Nodes) => {
+                    self.refresh_nodes();
+                    self.view = View::Nodes;
+
+                    should_render = true;
+                }
+                Some(Action::Quit) => {
+                    close_self();
+                }
+                _ => (),
+            },
+            _ => (),
+        };
+
+        if self.schedule_queries() {
+            should_render = true;
+        }
+
+        if self.schedule_preview() {
+            should_render = true;
+        }
+
+        if should_render {
+            self.save_view_state();
+        }
+
+        should_render
+    }
+
+    fn render(&mut self, rows: usize, cols: usize) {
+        self.rows = rows;
+
+        let rows = match (&self.prompt, &self.notice) {
+            (None, None) => rows,
+            _ => rows.saturating_sub(1),
+        };
+
+        let error_rows = self.error_rows(rows);
+        let rows = rows - error_rows;
+
+        let protected = self
+            .active_context()
+            .is_some_and(|c| self.safety.is_protected(c));
+
+        let help = self.show_help.then(|| self.help_lines());
+
+        match self.view {
+            View::Resources => self.renderer.render_cluster_state(
+                &self.cluster_state,
+                &self.selected_col,
+                protected,
+                help.as_deref(),
+                rows,
+                cols,
+            ),
+            View::PortForwards => {
+                self.renderer
+                    .render_port_forwards(&self.cluster_state, help.as_deref(), rows, cols)
+            }
+            View::Nodes => {
+                self.renderer
+                    .render_nodes(&self.cluster_state, help.as_deref(), rows, cols)
+            }
+            View::Favorites => {
+                self.renderer
+                    .render_favorites(&self.cluster_state, help.as_deref(), rows, cols)
+            }
+        }
+
+        if error_rows > 0 {
+            let dismiss = format!("{} dismiss", self.key_label(Action::DismissError));
+            let hint = match self.errors.latest() {
+                Some(error) if error.can_retry() => {
+                    format!("{} retry, {}", self.key_label(Action::Retry), dismiss)
+                }
+                _ => dismiss,
+            };
+            self.renderer
+                .render_errors(&self.errors, &hint, error_rows, cols);
+        }
+
+        if let Some(prompt) = &self.prompt {
+            self.renderer.render_prompt(prompt, cols);
+        } else if let Some(notice) = &self.notice {
+            self.renderer.render_notice(notice, cols);
+        }
+    }
+}
+
+impl State {
+    fn handle_mouse(&mut self, mouse: Mouse) -> bool {
+        match mouse {
+            Mouse::LeftClick(line, column) => {
+                let (col_type, item) = match self.renderer.hit_test(line.max(0) as usize, column) {
+                    Some(hit) => hit,
+                    None => return false,
+                };
+
+                self.selected_col = col_type;
+                if let Some(item) = item {
+                    self.cluster_state
+                        .select_item(ListDir::Line(item), &col_type);
+                }
+
+                true
+            }
+            Mouse::Hover(line, column) => {
+                self.hovered_col = self
+                    .renderer
+                    .hit_test(line.max(0) as usize, column)
+                    .map(|(col_type, _)| col_type);
+
+                false
+            }
+            // scrolling never wraps around at the ends of the column
+            Mouse::ScrollUp(lines) => {
+                let col_type = self.hovered_col.unwrap_or(self.selected_col);
+                self.cluster_state
+                    .select_item(ListDir::PageUp(lines), &col_type);
+
+                true
+            }
+            Mouse::ScrollDown(lines) => {
+                let col_type = self.hovered_col.unwrap_or(self.selected_col);
+                self.cluster_state
+                    .select_item(ListDir::PageDown(lines), &col_type);
+
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Map the navigation actions to the direction for moving the selection.
+    /// Page sizes are derived from the rows of the last render.
+    fn list_dir(&self, action: Action) -> Option<ListDir> {
+        let page_size = self.rows.saturating_sub(3).max(1);
+
+        match action {
+            Action::Up => Some(ListDir::Up),
+            Action::Down => Some(ListDir::Down),
+            Action::PageUp => Some(ListDir::PageUp(page_size)),
+            Action::PageDown => Some(ListDir::PageDown(page_size)),
+            Action::HalfPageUp => Some(ListDir::PageUp((page_size / 2).max(1))),
+            Action::HalfPageDown => Some(ListDir::PageDown((page_size / 2).max(1))),
+            Action::First => Some(ListDir::First),
+            Action::Last => Some(ListDir::Last),
+            _ => None,
+        }
+    }
+
+    /// Rows of the error area, at most half of the available rows.
+    fn error_rows(&self, rows: usize) -> usize {
+        match self.errors.latest() {
+            Some(error) => (render::error_lines(error).len() + 1)
+                .min(MAX_ERROR_ROWS)
+                .min(rows / 2),
+            None => 0,
+        }
+    }
+
+    fn key_label(&self, action: Action) -> String {
+        self.keymap
+            .keys(action)
+            .first()
+            .map(|k| k.to_string())
+            .unwrap_or_default()
+    }
+
+    /// Rerun the command of the latest error. Errors of the configuration
+    /// cannot be retried, they are only dismissed.
+    fn retry(&mut self) {
+        let error = match self.errors.latest() {
+            Some(error) => error,
+            None => return,
+        };
+
+        let mut command_ctx = match &error.command_ctx {
+            Some(command_ctx) if error.can_retry() => command_ctx.clone(),
+            _ => {
+                self.notice =
+                    Some("This error cannot be retried, fix the configuration".to_owned());
+                return;
+            }
+        };
+
+        // queries of the details may have been superseded in the meantime,
+        // so the retry becomes the latest one
+        if command_ctx.contains_key("request_id") {
+            let request_id = self.details_request();
+            command_ctx.insert("request_id".to_owned(), request_id.to_string());
+        }
+
+        kubernetes::retry(&command_ctx);
+        if let Some(query) = Query::from_context(&command_ctx) {
+            self.scheduler.track(query);
+        }
+        if let Some(command) = command_ctx.get("command") {
+            self.cluster_state.loading.start(command);
+        }
+        self.errors.dismiss();
+    }
+
+    /// List the keys and descriptions of all actions available in the
+    /// current view, the focused column and for the selected resource kind.
+    fn help_lines(&self) -> Vec<String> {
+        let resource_type = self.cluster_state.get_selected_item(&ColType::ResourceType);
+
+        let title = match (self.view, &resource_type) {
+            (View::PortForwards, _) => "Port Forwards".to_owned(),
+            (View::Favorites, _) => "Favorites".to_owned(),
+            (View::Nodes, _) => "Nodes".to_owned(),
+            (View::Resources, Some(resource_type)) if self.selected_col >= ColType::Resource => {
+                format!("{} ({})", self.selected_col.header(), resource_type)
+            }
+            (View::Resources, _) => self.selected_col.header().to_owned(),
+        };
+
+        let mut entries: Vec<(String, String)> = Action::ALL
+            .iter()
+            .filter(|a| a.is_available(&self.view, &self.selected_col, resource_type.as_deref()))
+            .map(|a| {
+                let keys = self
+                    .keymap
+                    .keys(*a)
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                (keys, a.description().to_owned())
+            })
+            .filter(|(keys, _)| !keys.is_empty())
+            .collect();
+
+        if self.view == View::Resources && self.selected_col == ColType::Resource {
+            entries.extend(
+                self.custom_actions
+                    .iter()
+                    .filter(|a| resource_type.as_deref().is_some_and(|t| a.applies_to(t)))
+                    .map(|a| (a.key.to_string(), format!("run \"{}\"", a.name))),
+            );
+        }
+
+        let keys_width = entries
+            .iter()
+            .map(|(keys, _)| console::measure_text_width(keys))
+            .max()
+            .unwrap_or(0);
+
+        let mut lines = vec![format!("Help: {}", title), "".to_owned()];
+        lines.extend(entries.iter().map(|(keys, description)| {
+            format!(
+                "{}  {}",
+                console::pad_str(keys, keys_width, console::Alignment::Left, None),
+                description
+            )
+        }));
+
+        lines
+    }
+
+    fn kubernetes_context(&self) -> Option<&str> {
+        self.userspace_configuration
+            .get("kubernetes_context")
+            .map(|s| s.as_str())
+    }
+
+    /// The context kubectl is run against, either configured in the layout
+    /// or the current context of the kubeconfig.
+    fn active_context(&self) -> Option<&str> {
+        self.kubernetes_context().or(self
+            .cluster_state
+            .context_info
+            .as_ref()
+            .map(|c| c.name.as_str()))
+    }
+
+    /// Run the action, unless the plugin is in read-only mode. In protected
+    /// contexts, the user must type the context name to confirm it. When
+    /// protected contexts are configured, but the context is not known yet,
+    /// the action is refused.
+    fn run_mutating_action(&mut self, action: MutatingAction) {
+        if self.safety.read_only {
+            self.notice = Some(format!("Read-only mode: cannot {}", action.description()));
+            self.abort_mutating_action(&action);
+            return;
+        }
+
+        match self.active_context() {
+            Some(context) if self.safety.is_protected(context) => {
+                self.prompt = Some(Prompt::new(
+                    &format!(
+                        "Type \"{}\" to {} in this protected context",
+                        context,
+                        action.description()
+                    ),
+                    "",
+                    PromptAction::Confirm(action),
+                ));
+                return;
+            }
+            None if self.safety.has_protected_contexts() => {
+                self.notice = Some(format!(
+                    "The context is not known yet: cannot {}",
+                    action.description()
+                ));
+                self.abort_mutating_action(&action);
+                return;
+            }
+            _ => (),
+        }
+
+        if action.needs_confirmation() {
+            self.prompt = Some(Prompt::new(
+                &format!("Really {}? [y/N]", action.description()),
+                "",
+                PromptAction::ConfirmYes(action),
+            ));
+            return;
+        }
+
+        self.execute_mutating_action(action);
+    }
+
+    fn execute_mutating_action(&mut self, action: MutatingAction) {
+        match action {
+            MutatingAction::Apply { path, file } => kubernetes::apply_resource(
+                &self.kubernetes_context(),
+                &path,
+                &file,
+                &self.apply_strategy,
+            ),
+            MutatingAction::Custom {
+                name,
+                args,
+                output,
+                resource_ref,
+            } => self.execute_custom_action(&name, &args, output, &resource_ref),
+            MutatingAction::Cordon { node } => {
+                kubernetes::run_node_action(&self.kubernetes_context(), "cordon", &node, &[])
+            }
+            MutatingAction::Uncordon { node } => {
+                kubernetes::run_node_action(&self.kubernetes_context(), "uncordon", &node, &[])
+            }
+            MutatingAction::Drain { node, options } => {
+                self.notice = Some(format!("Draining {}...", node));
+                kubernetes::run_node_action(&self.kubernetes_context(), "drain", &node, &options);
+            }
+        }
+    }
+
+    fn abort_mutating_action(&mut self, action: &MutatingAction) {
+        match action {
+            MutatingAction::Apply { file, .. } => remove_host_file(file),
+            MutatingAction::Custom { .. }
+            | MutatingAction::Cordon { .. }
+            | MutatingAction::Uncordon { .. }
+            | MutatingAction::Drain { .. } => (),
+        }
+    }
+
+    /// The custom action bound to the key, when it applies to the kind of
+    /// the resource selected in the focused resource column.
+    fn custom_action(&self, key: &KeyWithModifier) -> Option<CustomAction> {
+        if self.view != View::Resources || self.selected_col != ColType::Resource {
+            return None;
+        }
+
+        let resource_type = self
+            .cluster_state
+            .get_selected_item(&ColType::ResourceType)?;
+
+        self.custom_actions
+            .iter()
+            .find(|a| a.key == *key && a.applies_to(&resource_type))
+            .cloned()
+    }
+
+    fn run_custom_action(&mut self, action: CustomAction) {
+        let resource_ref = match self.selected_resource_ref() {
+            Some(resource_ref) => resource_ref,
+            None => return,
+        };
+
+        let placeholders = Placeholders {
+            context: self.active_context().unwrap_or_default().to_owned(),
+            namespace: resource_ref.namespace.clone(),
+            kind: resource_ref.resource_type.clone(),
+            name: resource_ref.name.clone(),
+            container: self
+                .cluster_state
+                .get_selected_resource()
+                .and_then(|r| r.containers.first().cloned())
+                .unwrap_or_default(),
+        };
+        let args = action.command_args(&placeholders);
+
+        if action.mutating {
+            self.run_mutating_action(MutatingAction::Custom {
+                name: action.name,
+                args,
+                output: action.output,
+                resource_ref,
+            });
+        } else {
+            self.execute_custom_action(&action.name, &args, action.output, &resource_ref);
+        }
+    }
+
+    fn execute_custom_action(
+        &mut self,
+        name: &str,
+        args: &[String],
+        output: CustomOutput,
+        resource_ref: &ResourceRef,
+    ) {
+        if args.is_empty() {
+            return;
+        }
+
+        match output {
+            CustomOutput::Pane => open_command_pane_floating(
+                CommandToRun {
+                    path: args[0].clone().into(),
+                    args: args[1..].to_vec(),
+                    cwd: None,
+                },
+                None,
+                BTreeMap::from([("command".to_owned(), "custom_action".to_owned())]),
+            ),
+            CustomOutput::Details => {
+                let request_id = self.details_request();
+                kubernetes::run_custom_action(name, args, resource_ref, request_id);
+                self.cluster_state.loading.start("custom_action");
+                self.selected_col = ColType::ResourceDetails;
+            }
+        }
+    }
+
+    fn selected_resource_ref(&self) -> Option<ResourceRef> {
+        Some(ResourceRef {
+            namespace: self.cluster_state.get_selected_item(&ColType::Namespace)?,
+            resource_type: self
+                .cluster_state
+                .get_selected_item(&ColType::ResourceType)?,
+            name: self.cluster_state.get_selected_item(&ColType::Resource)?,
+        })
+    }
+
+    /// The location currently shown. When the details of a resource are
+    /// shown, they define the location instead of the selected items.
+    fn location(&self) -> Location {
+        let details_ref = match self.cluster_state.resource_details_kind {
+            DetailsKind::Yaml => self.cluster_state.resource_details_ref.as_ref(),
+            _ => None,
+        };
+
+        let selection = match details_ref {
+            Some(resource_ref) => Selection::from(resource_ref),
+            None => Selection {
+                namespace: self.cluster_state.get_selected_item(&ColType::Namespace),
+                resource_type: self.cluster_state.get_selected_item(&ColType::ResourceType),
+                resource: self.cluster_state.get_selected_item(&ColType::Resource),
+            },
         };
 
-        let resource_type = match self.cluster_state.get_selected_item(&ColType::ResourceType) {
-            Some(resource_type) => resource_type,
+        Location {
+            context: self.kubernetes_context().map(|c| c.to_owned()),
+            selection,
+            details_position: details_ref.map(|_| {
+                (
+                    self.cluster_state
+                        .selected_resource_details_line
+                        .unwrap_or_default(),
+                    self.cluster_state.resource_details_offset,
+                )
+            }),
+            col_type: self.selected_col,
+        }
+    }
+
+    /// Record the current location and the details of the resource, that
+    /// are about to be shown, in the navigation history.
+    fn visit_details(&mut self, resource_ref: &ResourceRef) {
+        self.history.visit(self.location());
+        self.history.visit(Location {
+            context: self.kubernetes_context().map(|c| c.to_owned()),
+            selection: Selection::from(resource_ref),
+            details_position: Some((0, 0)),
+            col_type: ColType::ResourceDetails,
+        });
+    }
+
+    /// Restore a location from the navigation history, including the
+    /// context, selection, details and focused column.
+    fn restore(&mut self, location: Location) {
+        if location.context.as_deref() != self.kubernetes_context() {
+            self.switch_context(location.context.clone());
+        }
+
+        let selection = &location.selection;
+        if let (Some((line, offset)), Some(namespace), Some(resource_type), Some(name)) = (
+            location.details_position,
+            &selection.namespace,
+            &selection.resource_type,
+            &selection.resource,
+        ) {
+            self.cluster_state.restore_details_position(line, offset);
+            self.query_resource_details(&ResourceRef {
+                namespace: namespace.to_owned(),
+                resource_type: resource_type.to_owned(),
+                name: name.to_owned(),
+            });
+        }
+
+        self.cluster_state.select(location.selection);
+        self.selected_col = location.col_type;
+    }
+
+    fn view_state(&self) -> ViewState {
+        ViewState {
+            context: self.kubernetes_context().map(|c| c.to_owned()),
+            selection: Selection {
+                namespace: self.cluster_state.get_selected_item(&ColType::Namespace),
+                resource_type: self.cluster_state.get_selected_item(&ColType::ResourceType),
+                resource: self.cluster_state.get_selected_item(&ColType::Resource),
+            },
+            col_type: self.selected_col,
+            view: self.view,
+            resource_sort: self.cluster_state.resource_sort,
+            wrap_resource_details: self.cluster_state.wrap_resource_details,
+        }
+    }
+
+    /// Save the view state, when it changed. While a selection is still
+    /// being restored, the state is incomplete and not saved.
+    fn save_view_state(&mut self) {
+        if self.cluster_state.has_pending_selection() {
+            return;
+        }
+
+        let view_state = self.view_state();
+        if self.saved_view_state.as_ref() == Some(&view_state) {
+            return;
+        }
+
+        if let Err(e) = view_state.save(&self.state_dir, &self.instance) {
+            eprintln!("{}", e);
+        }
+
+        self.saved_view_state = Some(view_state);
+    }
+
+    /// Restore the view state of the last session. Namespaces, resource
+    /// types and resources, that no longer exist, are skipped. The details
+    /// are not restored, so the resource column is focused instead.
+    ///
+    /// A context configured in the layout takes precedence over the saved
+    /// one. The saved selection is then only restored, when it belongs to
+    /// the configured context.
+    fn restore_view_state(&mut self) {
+        let view_state = match ViewState::load(&self.state_dir, &self.instance) {
+            Ok(Some(view_state)) => view_state,
+            Ok(None) => return,
+            Err(e) => {
+                self.notice = Some(e);
+                return;
+            }
+        };
+
+        let same_context = match (self.kubernetes_context(), &view_state.context) {
+            (None, Some(context)) => {
+                self.userspace_configuration
+                    .insert("kubernetes_context".to_owned(), context.clone());
+                true
+            }
+            (configured, saved) => configured == saved.as_deref(),
+        };
+
+        self.cluster_state.resource_sort = view_state.resource_sort;
+        self.cluster_state.wrap_resource_details = view_state.wrap_resource_details;
+
+        self.selected_col = view_state.col_type.min(ColType::Resource);
+        self.view = match view_state.view {
+            // port forwards end with the session
+            View::PortForwards => View::Resources,
+            view => view,
+        };
+        if self.view == View::Nodes {
+            self.refresh_nodes();
+        }
+        if !self.cluster_state.favorites.items().is_empty() {
+            self.cluster_state.selected_favorite = Some(0);
+        }
+
+        if same_context {
+            self.cluster_state.select(view_state.selection.clone());
+        }
+        self.saved_view_state = Some(view_state);
+    }
+
+    /// Jump to the favorite selected in the favorites view. Resources are
+    /// opened in the details.
+    fn open_favorite(&mut self) {
+        let (context, resource_ref, selection) = match self.cluster_state.get_selected_favorite() {
+            Some(Favorite::Namespace { context, namespace }) => (
+                context.clone(),
+                None,
+                Selection {
+                    namespace: Some(namespace.clone()),
+                    ..Default::default()
+                },
+            ),
+            Some(Favorite::Resource {
+                context,
+                resource_ref,
+            }) => (
+                context.clone(),
+                Some(resource_ref.clone()),
+                Selection::from(resource_ref),
+            ),
             None => return,
         };
 
-        kubernetes::query_resources(k8s_context, &namespace, &resource_type);
+        if let Some(resource_ref) = &resource_ref {
+            self.visit_details(resource_ref);
+        }
+
+        if !context.is_empty() && self.active_context() != Some(context.as_str()) {
+            self.switch_context(Some(context));
+        }
+
+        match &resource_ref {
+            Some(resource_ref) => {
+                self.query_resource_details(resource_ref);
+
+                self.selected_col = ColType::ResourceDetails;
+            }
+            None => self.selected_col = ColType::Namespace,
+        }
+
+        self.cluster_state.select(selection);
+        self.view = View::Resources;
+    }
+
+    /// Run kubectl against another context and reload everything from it.
+    fn switch_context(&mut self, context: Option<String>) {
+        match context {
+            Some(context) => self
+                .userspace_configuration
+                .insert("kubernetes_context".to_owned(), context),
+            None => self.userspace_configuration.remove("kubernetes_context"),
+        };
+
+        self.cluster_state.clear();
+        self.scheduler.clear();
+        self.previewed = None;
+        kubernetes::query_context_info(&self.kubernetes_context());
+    }
+
+    /// Open the editor for the selected resource. When the loaded details
+    /// belong to another resource, they are queried first and the editor
+    /// is opened as soon as they arrive.
+    fn request_edit(&mut self) {
+        if self.selected_col != ColType::Resource && self.selected_col != ColType::ResourceDetails {
+            return;
+        }
+
+        let resource_ref = match self.selected_resource_ref() {
+            Some(resource_ref) => resource_ref,
+            None => return,
+        };
+
+        if self.safety.read_only {
+            self.notice = Some("Read-only mode: editing is disabled".to_owned());
+            return;
+        }
+
+        if self.cluster_state.resource_details.is_some()
+            && self.cluster_state.resource_details_kind == DetailsKind::Yaml
+            && self.cluster_state.resource_details_ref.as_ref() == Some(&resource_ref)
+        {
+            self.edit_resource();
+            return;
+        }
+
+        // the preview would supersede the query of the same details
+        self.query_resource_details(&resource_ref);
+        self.previewed = Some(resource_ref.clone());
+        self.edit_requested = Some(resource_ref);
+    }
+
+    /// Write the loaded resource details into the host folder and open it
+    /// with `$EDITOR` in a new command pane.
+    fn edit_resource(&mut self) {
+        if self.cluster_state.resource_details_kind != DetailsKind::Yaml {
+            return;
+        }
+
+        let (resource_ref, details) = match (
+            &self.cluster_state.resource_details_ref,
+            &self.cluster_state.resource_details,
+        ) {
+            (Some(resource_ref), Some(details)) => (resource_ref, details),
+            _ => return,
+        };
+
+        let file = format!(
+            ".zjk8s-{}-{}-{}.yaml",
+            resource_ref.namespace,
+            resource_ref.resource_type.to_lowercase(),
+            resource_ref.name
+        );
+        let content = format!("{}\n", details.join("\n"));
+
+        if let Err(e) = std::fs::write(PathBuf::from("/host").join(&file), &content) {
+            self.notice = Some(format!("Cannot write {}: {}", file, e));
+            return;
+        }
+
+        let path = get_plugin_ids()
+            .initial_cwd
+            .join(&file)
+            .to_string_lossy()
+            .to_string();
+
+        self.edited_files.insert(file.clone(), content);
+
+        open_command_pane(
+            CommandToRun {
+                path: "sh".into(),
+                args: vec![
+                    "-c".to_owned(),
+                    "exec ${EDITOR:-vi} \"$0\"".to_owned(),
+                    path.clone(),
+                ],
+                cwd: None,
+            },
+            BTreeMap::from([
+                ("command".to_owned(), "edit_resource".to_owned()),
+                ("path".to_owned(), path),
+                ("file".to_owned(), file),
+            ]),
+        );
+    }
+
+    /// Diff the edited file against the cluster. Once the diff is shown, the
+    /// changes are confirmed and applied, such that validation errors stop
+    /// them early.
+    fn apply_edited_file(&mut self, path: &str, file: &str) {
+        let original = self.edited_files.remove(file);
+        let edited = std::fs::read_to_string(PathBuf::from("/host").join(file)).ok();
+
+        if edited.is_none() {
+            self.notice = Some(format!("Cannot read {}, nothing applied", file));
+            remove_host_file(file);
+            return;
+        }
+
+        if edited == original {
+            self.notice = Some("No changes, nothing applied".to_owned());
+            remove_host_file(file);
+            return;
+        }
+
+        if self.safety.read_only {
+            self.notice = Some("Read-only mode: cannot apply the changes".to_owned());
+            remove_host_file(file);
+            return;
+        }
+
+        self.selected_col = ColType::ResourceDetails;
+        kubernetes::diff_resource(&self.kubernetes_context(), path, file);
+    }
+
+    fn handle_prompt_key(&mut self, key: &KeyWithModifier) {
+        let prompt = match &mut self.prompt {
+            Some(prompt) => prompt,
+            None => return,
+        };
+
+        let result = prompt.handle_key(key);
+        let action = prompt.action.clone();
+
+        let input = match result {
+            PromptResult::Pending => {
+                prompt.hint.clear();
+                return;
+            }
+            PromptResult::Complete => {
+                if action == PromptAction::Command {
+                    self.complete_command();
+                }
+                return;
+            }
+            PromptResult::Cancel => {
+                self.prompt = None;
+                if let PromptAction::Confirm(action) | PromptAction::ConfirmYes(action) = action {
+                    self.abort_mutating_action(&action);
+                }
+                return;
+            }
+            PromptResult::Submit(input) => input,
+        };
+
+        self.prompt = None;
+
+        match action {
+            PromptAction::PortForward(resource_ref) => {
+                if input.is_empty() {
+                    return;
+                }
+
+                let k8s_context = self.kubernetes_context().map(|s| s.to_owned());
+                self.cluster_state.start_port_forward(
+                    &k8s_context.as_deref(),
+                    resource_ref,
+                    &input,
+                );
+                self.view = View::PortForwards;
+            }
+            PromptAction::JumpToLine => match input.parse::<usize>() {
+                Ok(line) => self
+                    .cluster_state
+                    .select_item(ListDir::Line(line.saturating_sub(1)), &self.selected_col),
+                Err(_) => self.notice = Some(format!("\"{}\" is not a line number", input)),
+            },
+            PromptAction::Command => self.execute_command(&input),
+            PromptAction::ConfirmYes(action) => {
+                if matches!(input.to_lowercase().as_str(), "y" | "yes") {
+                    self.execute_mutating_action(action);
+                } else {
+                    self.notice = Some("Aborted".to_owned());
+                    self.abort_mutating_action(&action);
+                }
+            }
+            PromptAction::DrainOptions(node) => {
+                self.run_mutating_action(MutatingAction::Drain {
+                    node,
+                    options: input.split_whitespace().map(|o| o.to_owned()).collect(),
+                });
+            }
+            PromptAction::Confirm(action) => {
+                if Some(input.as_str()) == self.active_context() {
+                    self.execute_mutating_action(action);
+                } else {
+                    self.notice = Some("Confirmation did not match, aborted".to_owned());
+                    self.abort_mutating_action(&action);
+                }
+            }
+        }
+    }
+
+    fn completions(&self) -> Completions<'_> {
+        Completions {
+            aliases: self
+                .cluster_state
+                .resource_aliases
+                .as_deref()
+                .unwrap_or_default(),
+            resource_types: self
+                .cluster_state
+                .resource_types
+                .as_deref()
+                .unwrap_or_default(),
+            namespaces: self.cluster_state.namespaces.as_deref().unwrap_or_default(),
+            contexts: self.cluster_state.contexts.as_deref().unwrap_or_default(),
+        }
+    }
+
+    /// Complete the input of the command prompt and show the candidates,
+    /// when the input is ambiguous.
+    fn complete_command(&mut self) {
+        let input = match &self.prompt {
+            Some(prompt) => prompt.input.clone(),
+            None => return,
+        };
+
+        let (input, candidates) = command::complete(&input, &self.completions());
+
+        if let Some(prompt) = &mut self.prompt {
+            prompt.input = input;
+            prompt.hint = candidates.join("  ");
+        }
+    }
+
+    fn execute_command(&mut self, input: &str) {
+        if input.is_empty() {
+            return;
+        }
+
+        let command = match command::parse(input, &self.completions()) {
+            Ok(command) => command,
+            Err(e) => {
+                self.notice = Some(e);
+                return;
+            }
+        };
+
+        match command {
+            Command::Namespace(None) => self.selected_col = ColType::Namespace,
+            Command::Namespace(Some(namespace)) => {
+                if !self.completions().namespaces.contains(&namespace) {
+                    self.notice = Some(format!("Namespace \"{}\" not found", namespace));
+                    return;
+                }
+
+                self.cluster_state.select(Selection {
+                    namespace: Some(namespace),
+                    ..Default::default()
+                });
+                self.selected_col = ColType::ResourceType;
+            }
+            Command::Context(context) => {
+                let contexts = self.completions().contexts;
+                if !contexts.is_empty() && !contexts.contains(&context) {
+                    self.notice = Some(format!("Context \"{}\" not found", context));
+                    return;
+                }
+
+                self.switch_context(Some(context));
+                self.selected_col = ColType::Namespace;
+            }
+            Command::ResourceType(kind) => {
+                if !self.completions().resource_types.contains(&kind) {
+                    let namespace = self
+                        .cluster_state
+                        .get_selected_item(&ColType::Namespace)
+                        .unwrap_or_default();
+                    self.notice = Some(format!("No {} in namespace \"{}\"", kind, namespace));
+                    return;
+                }
+
+                self.cluster_state.select(Selection {
+                    resource_type: Some(kind),
+                    ..Default::default()
+                });
+                self.selected_col = ColType::Resource;
+            }
+        }
+    }
+
+    /// Query the ports of the selected pod or service. The prompt for the
+    /// local and remote port is opened, once the ports are known.
+    fn request_port_forward(&mut self) {
+        if self.selected_col != ColType::Resource {
+            return;
+        }
+
+        let resource_ref = match self.selected_resource_ref() {
+            Some(resource_ref) => resource_ref,
+            None => return,
+        };
+
+        if !kubernetes::supports_port_forward(&resource_ref.resource_type) {
+            return;
+        }
+
+        kubernetes::query_ports(&self.kubernetes_context(), &resource_ref);
+    }
+
+    fn prompt_port_forward(&mut self) {
+        let (resource_ref, ports) = match self.cluster_state.suggested_ports.take() {
+            Some(suggested_ports) => suggested_ports,
+            None => return,
+        };
+
+        let title = if ports.is_empty() {
+            format!("Forward {} (local:remote)", resource_ref.name)
+        } else {
+            format!(
+                "Forward {} [{}] (local:remote)",
+                resource_ref.name,
+                ports.join(", ")
+            )
+        };
+        let input = match ports.first() {
+            Some(port) => format!("{}:{}", port, port),
+            None => String::new(),
+        };
+
+        self.prompt = Some(Prompt::new(
+            &title,
+            &input,
+            PromptAction::PortForward(resource_ref),
+        ));
+    }
+
+    /// Id for a new query of the details column. The query supersedes a
+    /// pending edit request, whose details will not be shown anymore.
+    fn details_request(&mut self) -> u64 {
+        self.edit_requested = None;
+        self.scheduler.details_request()
+    }
+
+    fn query_resource_details(&mut self, resource_ref: &ResourceRef) {
+        let request_id = self.details_request();
+        kubernetes::query_resource_details(
+            self.kubernetes_context(),
+            &resource_ref.namespace,
+            &resource_ref.resource_type,
+            &resource_ref.name,
+            request_id,
+        );
+        self.cluster_state.loading.start("query_resource_details");
+    }
+
+    /// Preview the details of the selected resource, once the selection
+    /// rested on it for the preview delay. Returns whether they were queried.
+    fn schedule_preview(&mut self) -> bool {
+        let delay = match self.preview_delay {
+            Some(delay) => delay,
+            None => return false,
+        };
+
+        let resource_ref = match self.selected_resource_ref() {
+            Some(resource_ref)
+                if self.view == View::Resources
+                    && self.selected_col == ColType::Resource
+                    && self.prompt.is_none() =>
+            {
+                resource_ref
+            }
+            _ => {
+                self.preview = None;
+                return false;
+            }
+        };
+
+        if self.previewed.as_ref() == Some(&resource_ref)
+            || self.cluster_state.resource_details_ref.as_ref() == Some(&resource_ref)
+        {
+            self.preview = None;
+            return false;
+        }
+
+        let now = Utc::now();
+        match &self.preview {
+            Some((pending, due)) if *pending == resource_ref => {
+                if now < *due {
+                    return false;
+                }
+            }
+            _ => {
+                self.preview = Some((
+                    resource_ref,
+                    now + Duration::milliseconds((delay * 1000.0) as i64),
+                ));
+                set_timeout(delay);
+
+                return false;
+            }
+        }
+
+        self.preview = None;
+        self.query_resource_details(&resource_ref);
+        self.previewed = Some(resource_ref);
+
+        true
+    }
+
+    /// Request the lists, that are missing or outdated, from the scheduler
+    /// and dispatch the queries, that are due. Returns whether a query was
+    /// dispatched.
+    fn schedule_queries(&mut self) -> bool {
+        let now = Utc::now();
+        let mut cached = false;
+
+        // cached lists are shown right away, so the next column can be
+        // requested in the same pass
+        if self.cluster_state.namespaces.is_none() {
+            cached |= self.request_query(Query::Namespaces, now);
+        }
+
+        if let (true, Some(namespace)) = (
+            self.cluster_state.refresh_resource_types,
+            self.cluster_state.get_selected_item(&ColType::Namespace),
+        ) {
+            cached |= self.request_query(Query::ResourceTypes { namespace }, now);
+        }
+
+        if let (true, Some(namespace), Some(resource_type)) = (
+            self.cluster_state.refresh_resources,
+            self.cluster_state.get_selected_item(&ColType::Namespace),
+            self.cluster_state.get_selected_item(&ColType::ResourceType),
+        ) {
+            cached |= self.request_query(
+                Query::Resources {
+                    namespace,
+                    resource_type,
+                },
+                now,
+            );
+        }
+
+        let due = self.scheduler.due(now);
+        for query in &due {
+            query.run(&self.kubernetes_context());
+            self.cluster_state.loading.start(query.command());
+        }
+
+        if let Some(delay) = self.scheduler.next_due(now) {
+            set_timeout(delay);
+        }
+
+        cached || !due.is_empty()
+    }
+
+    /// Show the cached result of the query and request it from the
+    /// scheduler, when it is not cached or expired. Returns whether a cached
+    /// result was shown.
+    fn request_query(&mut self, query: Query, now: DateTime<Utc>) -> bool {
+        let kube_context = self.kubernetes_context().unwrap_or_default().to_owned();
+        let cached = self.cluster_state.load_cached(&kube_context, &query);
+
+        // the metrics of cached pods or nodes are outdated
+        if cached.is_some() && matches!(query, Query::Resources { .. }) {
+            self.refresh_metrics();
+        }
+
+        match cached {
+            Some(true) => true,
+            Some(false) => {
+                self.scheduler.request(query, now);
+                true
+            }
+            None => {
+                self.scheduler.request(query, now);
+                false
+            }
+        }
+    }
+
+    /// Drop the cached results of the context and query the shown lists and
+    /// details again.
+    fn force_refresh(&mut self) {
+        let kube_context = self.kubernetes_context().unwrap_or_default().to_owned();
+        self.cluster_state.cache.invalidate(&kube_context);
+
+        let now = Utc::now();
+        let namespace = self.cluster_state.get_selected_item(&ColType::Namespace);
+        let resource_type = self.cluster_state.get_selected_item(&ColType::ResourceType);
+
+        self.scheduler.request(Query::Namespaces, now);
+
+        if let Some(namespace) = &namespace {
+            self.scheduler.request(
+                Query::ResourceTypes {
+                    namespace: namespace.to_owned(),
+                },
+                now,
+            );
+        }
+
+        if let (Some(namespace), Some(resource_type)) = (namespace, resource_type) {
+            self.scheduler.request(
+                Query::Resources {
+                    namespace,
+                    resource_type,
+                },
+                now,
+            );
+        }
+
+        if self.cluster_state.resource_details_kind != DetailsKind::Yaml {
+            return;
+        }
+
+        if let (Some(resource_ref), Some(line)) = (
+            self.cluster_state.resource_details_ref.clone(),
+            self.cluster_state.selected_resource_details_line,
+        ) {
+            let offset = self.cluster_state.resource_details_offset;
+            self.cluster_state.restore_details_position(line, offset);
+            self.query_resource_details(&resource_ref);
+        }
+    }
+
+    /// Query the nodes with their metrics for the nodes view.
+    fn refresh_nodes(&self) {
+        kubernetes::query_nodes(&self.kubernetes_context());
+        kubernetes::query_node_metrics(&self.kubernetes_context());
+    }
+
+    fn schedule_refresh(&mut self) {
+        self.next_refresh = Utc::now() + Duration::milliseconds((REFRESH_INTERVAL * 1000.0) as i64);
+        set_timeout(REFRESH_INTERVAL);
+    }
+
+    /// Query the metrics, when pods are listed. Unavailable metrics are
+    /// queried again, e.g. once the metrics-server is installed.
+    fn refresh_metrics(&self) {
+        let (namespace, resource_type) = match (
+            self.cluster_state.get_selected_item(&ColType::Namespace),
+            self.cluster_state.get_selected_item(&ColType::ResourceType),
+        ) {
+            (Some(namespace), Some(resource_type)) => (namespace, resource_type),
+            _ => return,
+        };
+
+        if kubernetes::supports_metrics(&resource_type) {
+            kubernetes::query_metrics(&self.kubernetes_context(), &namespace, &resource_type);
+        }
+    }
+}
+
+fn remove_host_file(file: &str) {
+    if let Err(e) = std::fs::remove_file(PathBuf::from("/host").join(file)) {
+        eprintln!("Cannot remove {}: {}", file, e);
     }
 }
@@ -0,0 +1,136 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+use crate::kubernetes::Resource;
+use crate::scheduler::Query;
+
+// seconds a cached result is served without querying it again
+pub const DEFAULT_CACHE_TTL: u64 = 60;
+
+/// Parsed result of a query of the scheduler.
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryResult {
+    Namespaces(Vec<String>),
+    ResourceTypes(Vec<String>),
+    Resources(Vec<Resource>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct CacheKey {
+    // kubernetes context, empty for the current context of the kubeconfig
+    context: String,
+    query: Query,
+}
+
+/// Results of the queries for the lists of the columns, such that moving
+/// back and forth between namespaces and resource types shows them
+/// instantly. Expired results are still served, but queried again.
+#[derive(Debug)]
+pub struct Cache {
+    ttl: Duration,
+    entries: BTreeMap<CacheKey, (DateTime<Utc>, QueryResult)>,
+}
+
+impl Default for Cache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CACHE_TTL)
+    }
+}
+
+impl Cache {
+    pub fn new(ttl: u64) -> Self {
+        Self {
+            ttl: Duration::seconds(ttl as i64),
+            entries: BTreeMap::new(),
+        }
+    }
+
+    /// Age, after which cached results are queried again.
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    pub fn insert(&mut self, context: &str, query: Query, result: QueryResult) {
+        let key = CacheKey {
+            context: context.to_owned(),
+            query,
+        };
+
+        self.entries.insert(key, (Utc::now(), result));
+    }
+
+    /// The cached result of the query with the time it was queried and
+    /// whether it is still fresh.
+    pub fn get(&self, context: &str, query: &Query) -> Option<(&QueryResult, DateTime<Utc>, bool)> {
+        let key = CacheKey {
+            context: context.to_owned(),
+            query: query.clone(),
+        };
+
+        self.entries
+            .get(&key)
+            .map(|(queried, result)| (result, *queried, Utc::now() - *queried < self.ttl))
+    }
+
+    /// Forget all results of the context, e.g. to force a refresh.
+    pub fn invalidate(&mut self, context: &str) {
+        self.entries.retain(|key, _| key.context != context);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn namespaces() -> QueryResult {
+        QueryResult::Namespaces(vec!["default".to_owned()])
+    }
+
+    #[test]
+    fn results_are_fresh_within_the_ttl() {
+        let mut cache = Cache::new(60);
+        cache.insert("k8s", Query::Namespaces, namespaces());
+
+        let (result, _, fresh) = cache.get("k8s", &Query::Namespaces).unwrap();
+        assert_eq!(*result, namespaces());
+        assert!(fresh);
+    }
+
+    #[test]
+    fn expired_results_are_still_served() {
+        let mut cache = Cache::new(0);
+        cache.insert("k8s", Query::Namespaces, namespaces());
+
+        let (result, _, fresh) = cache.get("k8s", &Query::Namespaces).unwrap();
+        assert_eq!(*result, namespaces());
+        assert!(!fresh);
+    }
+
+    #[test]
+    fn results_are_cached_per_context_and_query() {
+        let mut cache = Cache::default();
+        cache.insert("k8s", Query::Namespaces, namespaces());
+
+        assert!(cache.get("other", &Query::Namespaces).is_none());
+        assert!(cache
+            .get(
+                "k8s",
+                &Query::ResourceTypes {
+                    namespace: "default".to_owned()
+                }
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn invalidate_only_drops_the_context() {
+        let mut cache = Cache::default();
+        cache.insert("k8s", Query::Namespaces, namespaces());
+        cache.insert("other", Query::Namespaces, namespaces());
+
+        cache.invalidate("k8s");
+
+        assert!(cache.get("k8s", &Query::Namespaces).is_none());
+        assert!(cache.get("other", &Query::Namespaces).is_some());
+    }
+}
@@ -0,0 +1,262 @@
+use itertools::Itertools;
+
+/// Commands of the `:` prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Namespace(Option<String>),
+    Context(String),
+    ResourceType(String),
+}
+
+/// Values the commands and their arguments are completed from.
+#[derive(Debug, Default)]
+pub struct Completions<'a> {
+    // alias and kind of every resource type, e.g. ("po", "Pod")
+    pub aliases: &'a [(String, String)],
+    pub resource_types: &'a [String],
+    pub namespaces: &'a [String],
+    pub contexts: &'a [String],
+}
+
+impl Completions<'_> {
+    /// Resolve an alias, plural or kind to the kind of the resource type.
+    fn kind(&self, alias: &str) -> Option<String> {
+        let alias = alias.to_lowercase();
+
+        self.resource_types
+            .iter()
+            .find(|t| t.to_lowercase() == alias)
+            .cloned()
+            .or_else(|| {
+                self.aliases
+                    .iter()
+                    .find(|(a, _)| *a == alias)
+                    .map(|(_, kind)| kind.clone())
+            })
+    }
+
+    fn commands(&self) -> Vec<String> {
+        ["ns", "ctx"]
+            .iter()
+            .map(|c| c.to_string())
+            .chain(self.aliases.iter().map(|(alias, _)| alias.clone()))
+            .chain(self.resource_types.iter().map(|t| t.to_lowercase()))
+            .unique()
+            .sorted()
+            .collect()
+    }
+}
+
+pub fn parse(input: &str, completions: &Completions) -> Result<Command, String> {
+    let mut words = input.split_whitespace();
+
+    let command = match words.next() {
+        Some(command) => command,
+        None => return Err("Empty command".to_owned()),
+    };
+    let argument = words.next().map(|a| a.to_owned());
+
+    if let Some(extra) = words.next() {
+        return Err(format!("Unexpected argument \"{}\"", extra));
+    }
+
+    match (command, argument) {
+        ("ns" | "namespace", argument) => Ok(Command::Namespace(argument)),
+        ("ctx" | "context", Some(context)) => Ok(Command::Context(context)),
+        ("ctx" | "context", None) => Err("Missing the context name".to_owned()),
+        (alias, None) => match completions.kind(alias) {
+            Some(kind) => Ok(Command::ResourceType(kind)),
+            None => Err(format!("Unknown resource type \"{}\"", alias)),
+        },
+        (_, Some(argument)) => Err(format!("Unexpected argument \"{}\"", argument)),
+    }
+}
+
+/// Complete the last word of the input. Returns the completed input and the
+/// candidates, when the word is ambiguous.
+pub fn complete(input: &str, completions: &Completions) -> (String, Vec<String>) {
+    let words: Vec<&str> = input.split_whitespace().collect();
+    let completing_argument = words.len() > 1 || (words.len() == 1 && input.ends_with(' '));
+
+    let (prefix, word) = match (completing_argument, input.rsplit_once(' ')) {
+        (true, Some((prefix, word))) => (format!("{} ", prefix.trim_end()), word),
+        _ => (String::new(), input.trim_start()),
+    };
+
+    let candidates: Vec<String> = match (completing_argument, words.first()) {
+        (false, _) => completions.commands(),
+        (true, Some(&"ns" | &"namespace")) => completions.namespaces.to_vec(),
+        (true, Some(&"ctx" | &"context")) => completions.contexts.to_vec(),
+        _ => vec![],
+    };
+
+    let matches: Vec<String> = candidates
+        .into_iter()
+        .filter(|c| c.starts_with(word))
+        .collect();
+
+    match matches.as_slice() {
+        [] => (input.to_owned(), vec![]),
+        [single] => {
+            let separator = if completing_argument { "" } else { " " };
+            (format!("{}{}{}", prefix, single, separator), vec![])
+        }
+        _ => (format!("{}{}", prefix, common_prefix(&matches)), matches),
+    }
+}
+
+fn common_prefix(words: &[String]) -> String {
+    let first = match words.first() {
+        Some(first) => first,
+        None => return String::new(),
+    };
+
+    let len = words
+        .iter()
+        .map(|w| {
+            first
+                .chars()
+                .zip(w.chars())
+                .take_while(|(a, b)| a == b)
+                .count()
+        })
+        .min()
+        .unwrap_or_default();
+
+    first.chars().take(len).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    struct Fixture {
+        aliases: Vec<(String, String)>,
+        resource_types: Vec<String>,
+        namespaces: Vec<String>,
+        contexts: Vec<String>,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self {
+                aliases: vec![
+                    ("po".to_owned(), "Pod".to_owned()),
+                    ("pods".to_owned(), "Pod".to_owned()),
+                    ("deploy".to_owned(), "Deployment".to_owned()),
+                ],
+                resource_types: strings(&["Pod", "Deployment", "Service"]),
+                namespaces: strings(&["default", "kube-public", "kube-system"]),
+                contexts: strings(&["prod", "staging"]),
+            }
+        }
+
+        fn completions(&self) -> Completions<'_> {
+            Completions {
+                aliases: &self.aliases,
+                resource_types: &self.resource_types,
+                namespaces: &self.namespaces,
+                contexts: &self.contexts,
+            }
+        }
+    }
+
+    #[test]
+    fn aliases_resolve_to_kinds() {
+        let fixture = Fixture::new();
+        let completions = fixture.completions();
+
+        assert_eq!(
+            parse("po", &completions),
+            Ok(Command::ResourceType("Pod".to_owned()))
+        );
+        assert_eq!(
+            parse("deploy", &completions),
+            Ok(Command::ResourceType("Deployment".to_owned()))
+        );
+        assert_eq!(
+            parse("service", &completions),
+            Ok(Command::ResourceType("Service".to_owned()))
+        );
+        assert!(parse("unknown", &completions).is_err());
+        assert!(parse("po extra", &completions).is_err());
+    }
+
+    #[test]
+    fn namespace_with_and_without_argument() {
+        let fixture = Fixture::new();
+        let completions = fixture.completions();
+
+        assert_eq!(
+            parse("ns kube-system", &completions),
+            Ok(Command::Namespace(Some("kube-system".to_owned())))
+        );
+        assert_eq!(parse("ns", &completions), Ok(Command::Namespace(None)));
+    }
+
+    #[test]
+    fn context_requires_an_argument() {
+        let fixture = Fixture::new();
+        let completions = fixture.completions();
+
+        assert_eq!(
+            parse("ctx prod", &completions),
+            Ok(Command::Context("prod".to_owned()))
+        );
+        assert_eq!(
+            parse("ctx", &completions),
+            Err("Missing the context name".to_owned())
+        );
+        assert_eq!(parse("  ", &completions), Err("Empty command".to_owned()));
+    }
+
+    #[test]
+    fn unique_commands_are_completed() {
+        let fixture = Fixture::new();
+        let completions = fixture.completions();
+
+        assert_eq!(
+            complete("se", &completions),
+            ("service ".to_owned(), vec![])
+        );
+        assert_eq!(complete("ct", &completions), ("ctx ".to_owned(), vec![]));
+        assert_eq!(complete("xyz", &completions), ("xyz".to_owned(), vec![]));
+    }
+
+    #[test]
+    fn arguments_are_completed_after_a_trailing_space() {
+        let fixture = Fixture::new();
+        let completions = fixture.completions();
+
+        assert_eq!(
+            complete("ctx ", &completions),
+            ("ctx ".to_owned(), strings(&["prod", "staging"]))
+        );
+        assert_eq!(
+            complete("ctx st", &completions),
+            ("ctx staging".to_owned(), vec![])
+        );
+    }
+
+    #[test]
+    fn ambiguous_words_complete_to_the_common_prefix() {
+        let fixture = Fixture::new();
+        let completions = fixture.completions();
+
+        assert_eq!(
+            complete("ns k", &completions),
+            (
+                "ns kube-".to_owned(),
+                strings(&["kube-public", "kube-system"])
+            )
+        );
+        assert_eq!(
+            complete("po", &completions),
+            ("po".to_owned(), strings(&["po", "pod", "pods"]))
+        );
+    }
+}
@@ -0,0 +1,54 @@
+use miette::{Diagnostic, NamedSource, Result, SourceSpan};
+use thiserror::Error;
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("Invalid configuration")]
+#[diagnostic(help("Value must be \"true\" or \"false\""))]
+struct InvalidBool {
+    #[source_code]
+    src: NamedSource<String>,
+
+    #[label("This error occured")]
+    bad_bit: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("Invalid configuration")]
+#[diagnostic(help("Value must be a positive number"))]
+struct InvalidNumber {
+    #[source_code]
+    src: NamedSource<String>,
+
+    #[label("This error occured")]
+    bad_bit: SourceSpan,
+}
+
+/// Parse the boolean configuration value `key`, falling back to `default`
+/// when it is not configured.
+pub fn parse_bool(key: &str, value: Option<&str>, default: bool) -> Result<bool> {
+    match value {
+        None => Ok(default),
+        Some("true") => Ok(true),
+        Some("false") => Ok(false),
+        Some(s) => Err(InvalidBool {
+            src: NamedSource::new("layout.kdl", format!("{} \"{}\"", key, s)),
+            bad_bit: (key.len() + 2, s.len()).into(),
+        }
+        .into()),
+    }
+}
+
+/// Parse the numeric configuration value `key`, falling back to `default`
+/// when it is not configured.
+pub fn parse_number(key: &str, value: Option<&str>, default: u64) -> Result<u64> {
+    match value {
+        None => Ok(default),
+        Some(s) => s.parse().map_err(|_| {
+            InvalidNumber {
+                src: NamedSource::new("layout.kdl", format!("{} \"{}\"", key, s)),
+                bad_bit: (key.len() + 2, s.len()).into(),
+            }
+            .into()
+        }),
+    }
+}
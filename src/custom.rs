@@ -0,0 +1,224 @@
+use miette::{Diagnostic, NamedSource, Result, SourceSpan};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use thiserror::Error;
+
+use zellij_tile::prelude::*;
+
+use crate::config::parse_bool;
+use crate::keymap::Keymap;
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("Invalid custom action")]
+#[diagnostic(help(
+    "Custom actions are configured with \"action_<name>_command\", \"action_<name>_key\", \"action_<name>_kinds\", \"action_<name>_output\" and \"action_<name>_mutating\""
+))]
+struct InvalidCustomAction {
+    #[source_code]
+    src: NamedSource<String>,
+
+    #[label("This error occured")]
+    bad_bit: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("Conflicting keybinding")]
+#[diagnostic(help("Custom actions cannot use keys, that are bound to other actions"))]
+struct ConflictingCustomKey {
+    #[source_code]
+    src: NamedSource<String>,
+
+    #[label("This error occured")]
+    bad_bit: SourceSpan,
+}
+
+const FIELDS: [&str; 5] = ["command", "key", "kinds", "output", "mutating"];
+
+/// Where the output of a custom action goes.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum CustomOutput {
+    #[default]
+    Pane,
+    Details,
+}
+
+/// A command declared in the plugin configuration, that is run for the
+/// selected resource, e.g.
+///
+/// ```kdl
+/// action_logs_command "kubectl logs --follow --context {context} --namespace {namespace} {name} --container {container}"
+/// action_logs_key "o"
+/// action_logs_kinds "Pod"
+/// action_logs_output "pane"
+/// action_logs_mutating "false"
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct CustomAction {
+    pub name: String,
+    pub key: KeyWithModifier,
+
+    // kinds the action applies to, all kinds when empty
+    pub kinds: Vec<String>,
+    pub command: String,
+    pub output: CustomOutput,
+
+    // mutating actions are guarded like the other modifications. Actions
+    // are mutating, unless they are declared otherwise, such that a command
+    // cannot bypass the read-only mode by leaving out the flag.
+    pub mutating: bool,
+}
+
+/// Values for the placeholders in the command of a custom action.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Placeholders {
+    pub context: String,
+    pub namespace: String,
+    pub kind: String,
+    pub name: String,
+    pub container: String,
+}
+
+impl CustomAction {
+    pub fn applies_to(&self, kind: &str) -> bool {
+        self.kinds.is_empty() || self.kinds.iter().any(|k| k == kind)
+    }
+
+    /// Split the command into its arguments and fill in the placeholders.
+    /// Values are substituted per argument, so they are never split.
+    pub fn command_args(&self, placeholders: &Placeholders) -> Vec<String> {
+        self.command
+            .split_whitespace()
+            .map(|arg| {
+                arg.replace("{context}", &placeholders.context)
+                    .replace("{namespace}", &placeholders.namespace)
+                    .replace("{kind}", &placeholders.kind)
+                    .replace("{name}", &placeholders.name)
+                    .replace("{container}", &placeholders.container)
+            })
+            .collect()
+    }
+}
+
+/// Collect the custom actions from the `action_<name>_<field>` values of the
+/// plugin configuration. Their keys must not be bound in the keymap.
+pub fn custom_actions(
+    configuration: &BTreeMap<String, String>,
+    keymap: &Keymap,
+) -> Result<Vec<CustomAction>> {
+    let mut fields: BTreeMap<&str, BTreeMap<&str, &str>> = BTreeMap::new();
+
+    for (key, value) in configuration {
+        let rest = match key.strip_prefix("action_") {
+            Some(rest) => rest,
+            None => continue,
+        };
+
+        let field = FIELDS.iter().find_map(|field| {
+            rest.strip_suffix(field)
+                .and_then(|name| name.strip_suffix('_'))
+                .filter(|name| !name.is_empty())
+                .map(|name| (name, *field))
+        });
+
+        match field {
+            Some((name, field)) => {
+                fields.entry(name).or_default().insert(field, value);
+            }
+            None => {
+                return Err(InvalidCustomAction {
+                    src: NamedSource::new("layout.kdl", key.to_owned()),
+                    bad_bit: (0, key.len()).into(),
+                }
+                .into());
+            }
+        }
+    }
+
+    let mut actions: Vec<CustomAction> = vec![];
+
+    for (name, fields) in fields {
+        let required = |field: &str| -> Result<&str> {
+            fields.get(field).copied().ok_or_else(|| {
+                let src = format!("action_{}_{} is missing", name, field);
+                InvalidCustomAction {
+                    bad_bit: (0, src.len()).into(),
+                    src: NamedSource::new("layout.kdl", src),
+                }
+                .into()
+            })
+        };
+
+        let command = required("command")?;
+        let key = required("key")?;
+
+        let src = format!("action_{}_key \"{}\"", name, key);
+        let key_offset = src.len() - key.len() - 1;
+
+        let key = match KeyWithModifier::from_str(key) {
+            Ok(key) => key,
+            Err(_) => {
+                return Err(InvalidCustomAction {
+                    src: NamedSource::new("layout.kdl", src),
+                    bad_bit: (key_offset, key.len()).into(),
+                }
+                .into());
+            }
+        };
+
+        let bound = keymap
+            .action(&key)
+            .map(|action| action.name().to_owned())
+            .or_else(|| {
+                actions
+                    .iter()
+                    .find(|a| a.key == key)
+                    .map(|a| format!("action_{}", a.name))
+            });
+
+        if let Some(other) = bound {
+            return Err(ConflictingCustomKey {
+                src: NamedSource::new(
+                    "layout.kdl",
+                    format!("{} (already bound to \"{}\")", src, other),
+                ),
+                bad_bit: (key_offset, key.to_string().len()).into(),
+            }
+            .into());
+        }
+
+        let output = match fields.get("output").copied() {
+            None | Some("pane") => CustomOutput::Pane,
+            Some("details") => CustomOutput::Details,
+            Some(output) => {
+                let src = format!("action_{}_output \"{}\"", name, output);
+                return Err(InvalidCustomAction {
+                    bad_bit: (src.len() - output.len() - 1, output.len()).into(),
+                    src: NamedSource::new("layout.kdl", src),
+                }
+                .into());
+            }
+        };
+
+        actions.push(CustomAction {
+            name: name.to_owned(),
+            key,
+            kinds: fields
+                .get("kinds")
+                .copied()
+                .unwrap_or_default()
+                .split([' ', ','])
+                .filter(|k| !k.is_empty())
+                .map(|k| k.to_owned())
+                .collect(),
+            command: command.to_owned(),
+            output,
+            mutating: parse_bool(
+                &format!("action_{}_mutating", name),
+                fields.get("mutating").copied(),
+                true,
+            )?,
+        });
+    }
+
+    Ok(actions)
+}
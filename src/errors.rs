@@ -0,0 +1,72 @@
+use miette::Report;
+use std::collections::BTreeMap;
+
+/// An error, that is shown in the error area until it is dismissed.
+#[derive(Debug)]
+pub struct PluginError {
+    pub report: Report,
+
+    // context of the failed command, used to retry it
+    pub command_ctx: Option<BTreeMap<String, String>>,
+}
+
+impl PluginError {
+    pub fn command(&self) -> Option<&str> {
+        self.command_ctx
+            .as_ref()?
+            .get("command")
+            .map(|c| c.as_str())
+    }
+
+    pub fn can_retry(&self) -> bool {
+        self.command_ctx
+            .as_ref()
+            .is_some_and(|c| c.contains_key("args"))
+    }
+}
+
+/// Errors of failed commands and invalid configuration values. They do not
+/// block the plugin, the latest one is shown until it is dismissed.
+#[derive(Debug, Default)]
+pub struct Errors {
+    items: Vec<PluginError>,
+}
+
+impl Errors {
+    /// Add the error. An error of the same command is replaced, such that
+    /// repeated failures, e.g. of refreshes, do not pile up.
+    pub fn push(&mut self, report: Report, command_ctx: Option<BTreeMap<String, String>>) {
+        let error = PluginError {
+            report,
+            command_ctx,
+        };
+
+        if let Some(command) = error.command() {
+            let command = command.to_owned();
+            self.items.retain(|e| e.command() != Some(&command));
+        }
+
+        self.items.push(error);
+    }
+
+    /// Remove the errors of a command, that succeeded since.
+    pub fn resolve(&mut self, command: &str) {
+        self.items.retain(|e| e.command() != Some(command));
+    }
+
+    pub fn latest(&self) -> Option<&PluginError> {
+        self.items.last()
+    }
+
+    pub fn dismiss(&mut self) -> Option<PluginError> {
+        self.items.pop()
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
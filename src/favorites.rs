@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::kubernetes::ResourceRef;
+
+// file in the state folder, see [`crate::session::state_dir`]
+const FAVORITES_FILE: &str = "favorites.json";
+
+/// A starred namespace or resource in a context.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Favorite {
+    Namespace {
+        context: String,
+        namespace: String,
+    },
+    Resource {
+        context: String,
+        #[serde(flatten)]
+        resource_ref: ResourceRef,
+    },
+}
+
+impl Favorite {
+    pub fn label(&self) -> String {
+        match self {
+            Favorite::Namespace { context, namespace } => {
+                format!("{}  {}", context, namespace)
+            }
+            Favorite::Resource {
+                context,
+                resource_ref,
+            } => format!(
+                "{}  {}/{}/{}",
+                context,
+                resource_ref.namespace,
+                resource_ref.resource_type.to_lowercase(),
+                resource_ref.name
+            ),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Favorites {
+    // state folder, the favorites are saved to
+    dir: PathBuf,
+    items: Vec<Favorite>,
+}
+
+impl Favorites {
+    /// No favorites, that are saved to the state folder.
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_owned(),
+            items: vec![],
+        }
+    }
+
+    /// Read the favorites from the state folder. A missing file results in
+    /// no favorites.
+    pub fn load(dir: &Path) -> Result<Self, String> {
+        let content = match std::fs::read_to_string(dir.join(FAVORITES_FILE)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new(dir)),
+            Err(e) => return Err(format!("Cannot read favorites: {}", e)),
+        };
+
+        serde_json::from_str(&content)
+            .map(|items| Self {
+                dir: dir.to_owned(),
+                items,
+            })
+            .map_err(|e| format!("Cannot parse favorites: {}", e))
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.items)
+            .map_err(|e| format!("Cannot serialize favorites: {}", e))?;
+
+        std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(self.dir.join(FAVORITES_FILE), content))
+            .map_err(|e| format!("Cannot save favorites: {}", e))
+    }
+
+    pub fn items(&self) -> &[Favorite] {
+        &self.items
+    }
+
+    pub fn contains(&self, favorite: &Favorite) -> bool {
+        self.items.contains(favorite)
+    }
+
+    /// Star the favorite or unstar it, when it is already starred. The
+    /// favorites are saved right away.
+    pub fn toggle(&mut self, favorite: Favorite) -> Result<(), String> {
+        match self.items.iter().position(|f| *f == favorite) {
+            Some(index) => {
+                self.items.remove(index);
+            }
+            None => self.items.push(favorite),
+        }
+
+        self.save()
+    }
+}
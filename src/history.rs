@@ -0,0 +1,89 @@
+use crate::kubernetes::Selection;
+use crate::render::ColType;
+
+// locations kept in the history, older ones are dropped
+const MAX_LOCATIONS: usize = 100;
+
+/// A visited location with everything needed to restore it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Location {
+    pub context: Option<String>,
+    pub selection: Selection,
+
+    // selected line and horizontal scroll position of the details, when
+    // they were shown for the selected resource
+    pub details_position: Option<(usize, usize)>,
+    pub col_type: ColType,
+}
+
+impl Location {
+    fn same_place(&self, other: &Location) -> bool {
+        self.context == other.context && self.selection == other.selection
+    }
+}
+
+/// Stack of visited locations with a cursor, such that one can go back and
+/// forward like in a browser.
+#[derive(Debug, Default)]
+pub struct History {
+    locations: Vec<Location>,
+    current: usize,
+}
+
+impl History {
+    /// Record the location as the current one. Locations ahead of the current
+    /// one are dropped. If the location points to the same resource as the
+    /// current one, it is updated instead.
+    pub fn visit(&mut self, location: Location) {
+        self.locations.truncate(self.current + 1);
+
+        match self.locations.last_mut() {
+            Some(last) if last.same_place(&location) => *last = location,
+            _ => self.locations.push(location),
+        }
+
+        if self.locations.len() > MAX_LOCATIONS {
+            self.locations.remove(0);
+        }
+
+        self.current = self.locations.len() - 1;
+    }
+
+    /// Go back to the previous location. The location the user is currently
+    /// at is recorded first, so it can be reached by going forward.
+    pub fn back(&mut self, current: Location) -> Option<Location> {
+        self.update_or_visit(current);
+
+        if self.current == 0 {
+            return None;
+        }
+
+        self.current -= 1;
+        self.locations.get(self.current).cloned()
+    }
+
+    /// Go forward to the next location, after going back.
+    pub fn forward(&mut self, current: Location) -> Option<Location> {
+        if self.current + 1 >= self.locations.len() {
+            return None;
+        }
+
+        self.update_or_visit(current);
+
+        if self.current + 1 >= self.locations.len() {
+            return None;
+        }
+
+        self.current += 1;
+        self.locations.get(self.current).cloned()
+    }
+
+    /// Update the current location in place, keeping the locations ahead of
+    /// it. When the user moved on to another resource, it is visited instead.
+    fn update_or_visit(&mut self, location: Location) {
+        match self.locations.get_mut(self.current) {
+            Some(current) if current.same_place(&location) => *current = location,
+            _ => self.visit(location),
+        }
+    }
+}
@@ -0,0 +1,440 @@
+use miette::{Diagnostic, NamedSource, Result, SourceSpan};
+use std::collections::BTreeMap;
+use std::str::FromStr;
+use thiserror::Error;
+
+use zellij_tile::prelude::*;
+
+use crate::kubernetes::supports_port_forward;
+use crate::related::supports_related;
+use crate::render::{ColType, View};
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("Invalid keybinding")]
+#[diagnostic(help("Keybindings must be configured as \"keybind_<action>\" \"<key>, <key>\""))]
+struct InvalidKeybinding {
+    #[source_code]
+    src: NamedSource<String>,
+
+    #[label("This error occured")]
+    bad_bit: SourceSpan,
+}
+
+#[derive(Error, Debug, Diagnostic)]
+#[error("Conflicting keybinding")]
+#[diagnostic(help("Each key can only be bound to a single action"))]
+struct ConflictingKeybinding {
+    #[source_code]
+    src: NamedSource<String>,
+
+    #[label("This error occured")]
+    bad_bit: SourceSpan,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Action {
+    Left,
+    Right,
+    Up,
+    Down,
+    PageUp,
+    PageDown,
+    HalfPageUp,
+    HalfPageDown,
+    First,
+    Last,
+    JumpToLine,
+    Command,
+    ScrollLeft,
+    ScrollRight,
+    ToggleWrap,
+    Sort,
+    ReverseSort,
+    Tree,
+    Related,
+    Refresh,
+    Back,
+    Forward,
+    Select,
+    Quit,
+    Edit,
+    PortForward,
+    PortForwards,
+    StopPortForward,
+    Favorite,
+    Favorites,
+    Nodes,
+    Cordon,
+    Uncordon,
+    Drain,
+    Retry,
+    DismissError,
+    Help,
+}
+
+impl Action {
+    pub const ALL: [Action; 37] = [
+        Action::Left,
+        Action::Right,
+        Action::Up,
+        Action::Down,
+        Action::PageUp,
+        Action::PageDown,
+        Action::HalfPageUp,
+        Action::HalfPageDown,
+        Action::First,
+        Action::Last,
+        Action::JumpToLine,
+        Action::Command,
+        Action::ScrollLeft,
+        Action::ScrollRight,
+        Action::ToggleWrap,
+        Action::Sort,
+        Action::ReverseSort,
+        Action::Tree,
+        Action::Related,
+        Action::Refresh,
+        Action::Back,
+        Action::Forward,
+        Action::Select,
+        Action::Quit,
+        Action::Edit,
+        Action::PortForward,
+        Action::PortForwards,
+        Action::StopPortForward,
+        Action::Favorite,
+        Action::Favorites,
+        Action::Nodes,
+        Action::Cordon,
+        Action::Uncordon,
+        Action::Drain,
+        Action::Retry,
+        Action::DismissError,
+        Action::Help,
+    ];
+
+    /// Name of the action as used in the `keybind_<name>` configuration.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Action::Left => "left",
+            Action::Right => "right",
+            Action::Up => "up",
+            Action::Down => "down",
+            Action::PageUp => "page_up",
+            Action::PageDown => "page_down",
+            Action::HalfPageUp => "half_page_up",
+            Action::HalfPageDown => "half_page_down",
+            Action::First => "first",
+            Action::Last => "last",
+            Action::JumpToLine => "jump_to_line",
+            Action::Command => "command",
+            Action::ScrollLeft => "scroll_left",
+            Action::ScrollRight => "scroll_right",
+            Action::ToggleWrap => "toggle_wrap",
+            Action::Sort => "sort",
+            Action::ReverseSort => "reverse_sort",
+            Action::Tree => "tree",
+            Action::Related => "related",
+            Action::Refresh => "refresh",
+            Action::Back => "back",
+            Action::Forward => "forward",
+            Action::Select => "select",
+            Action::Quit => "quit",
+            Action::Edit => "edit",
+            Action::PortForward => "port_forward",
+            Action::PortForwards => "port_forwards",
+            Action::StopPortForward => "stop_port_forward",
+            Action::Favorite => "favorite",
+            Action::Favorites => "favorites",
+            Action::Nodes => "nodes",
+            Action::Cordon => "cordon",
+            Action::Uncordon => "uncordon",
+            Action::Drain => "drain",
+            Action::Retry => "retry",
+            Action::DismissError => "dismiss_error",
+            Action::Help => "help",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Left => "focus the column on the left",
+            Action::Right => "focus the column on the right",
+            Action::Up => "select the previous item",
+            Action::Down => "select the next item",
+            Action::PageUp => "move one page up",
+            Action::PageDown => "move one page down",
+            Action::HalfPageUp => "move half a page up",
+            Action::HalfPageDown => "move half a page down",
+            Action::First => "select the first item",
+            Action::Last => "select the last item",
+            Action::JumpToLine => "jump to a line number",
+            Action::Command => "jump to a resource type, namespace or context",
+            Action::ScrollLeft => "scroll the details to the left",
+            Action::ScrollRight => "scroll the details to the right",
+            Action::ToggleWrap => "toggle wrapping of long lines",
+            Action::Sort => "sort by name, age, status or restarts",
+            Action::ReverseSort => "reverse the sort order",
+            Action::Tree => "show the owner tree of the resource",
+            Action::Related => "list the related resources",
+            Action::Refresh => "query the lists again, bypassing the cache",
+            Action::Back => "go back in the navigation history",
+            Action::Forward => "go forward in the navigation history",
+            Action::Select => "show the details or jump to the related resource",
+            Action::Quit => "close the view or plugin",
+            Action::Edit => "edit the resource in $EDITOR",
+            Action::PortForward => "forward a port of the resource",
+            Action::PortForwards => "toggle the port forward list",
+            Action::StopPortForward => "stop the selected port forward",
+            Action::Favorite => "star or unstar the item",
+            Action::Favorites => "toggle the favorites list",
+            Action::Nodes => "toggle the node overview",
+            Action::Cordon => "mark the node as unschedulable",
+            Action::Uncordon => "mark the node as schedulable",
+            Action::Drain => "evict all pods from the node",
+            Action::Retry => "retry the failed command",
+            Action::DismissError => "dismiss the error",
+            Action::Help => "toggle this help",
+        }
+    }
+
+    /// Whether the action has an effect in the view, the focused column and
+    /// for the kind of the selected resource.
+    pub fn is_available(
+        &self,
+        view: &View,
+        col_type: &ColType,
+        resource_type: Option<&str>,
+    ) -> bool {
+        let global = matches!(
+            self,
+            Action::Up
+                | Action::Down
+                | Action::PageUp
+                | Action::PageDown
+                | Action::HalfPageUp
+                | Action::HalfPageDown
+                | Action::First
+                | Action::Last
+                | Action::Quit
+                | Action::Retry
+                | Action::DismissError
+                | Action::Help
+        );
+
+        match (view, self) {
+            (View::PortForwards, Action::PortForwards | Action::StopPortForward) => true,
+            (View::PortForwards, _) => global,
+            (View::Favorites, Action::Select | Action::Favorite | Action::Favorites) => true,
+            (View::Favorites, _) => global,
+            (
+                View::Nodes,
+                Action::Nodes | Action::Cordon | Action::Uncordon | Action::Drain | Action::Refresh,
+            ) => true,
+            (View::Nodes, _) => global,
+            (View::Resources, Action::StopPortForward) => false,
+            (View::Resources, Action::Cordon | Action::Uncordon | Action::Drain) => false,
+            (View::Resources, Action::Favorite) => {
+                matches!(col_type, ColType::Namespace | ColType::Resource)
+            }
+            (View::Resources, Action::Select) => {
+                matches!(col_type, ColType::Resource | ColType::ResourceDetails)
+            }
+            (View::Resources, Action::Sort | Action::ReverseSort | Action::Tree) => {
+                *col_type == ColType::Resource
+            }
+            (View::Resources, Action::Related) => {
+                *col_type == ColType::Resource && resource_type.is_some_and(supports_related)
+            }
+            (View::Resources, Action::ScrollLeft | Action::ScrollRight | Action::ToggleWrap) => {
+                *col_type == ColType::ResourceDetails
+            }
+            (View::Resources, Action::Edit) => {
+                matches!(col_type, ColType::Resource | ColType::ResourceDetails)
+            }
+            (View::Resources, Action::PortForward) => {
+                *col_type == ColType::Resource && resource_type.is_some_and(supports_port_forward)
+            }
+            (View::Resources, _) => true,
+        }
+    }
+
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Left => &["Left", "h"],
+            Action::Right => &["Right", "l"],
+            Action::Up => &["Up", "k"],
+            Action::Down => &["Down", "j"],
+            Action::PageUp => &["PageUp", "ctrl b"],
+            Action::PageDown => &["PageDown", "ctrl f"],
+            Action::HalfPageUp => &["ctrl u"],
+            Action::HalfPageDown => &["ctrl d"],
+            Action::First => &["Home", "g"],
+            Action::Last => &["End", "G"],
+            Action::JumpToLine => &["ctrl g"],
+            Action::Command => &[":"],
+            Action::ScrollLeft => &["H"],
+            Action::ScrollRight => &["L"],
+            Action::ToggleWrap => &["w"],
+            Action::Sort => &["s"],
+            Action::ReverseSort => &["S"],
+            Action::Tree => &["t"],
+            Action::Related => &["r"],
+            Action::Refresh => &["ctrl r"],
+            Action::Back => &["["],
+            Action::Forward => &["]"],
+            Action::Select => &["Enter"],
+            Action::Quit => &["Esc", "q"],
+            Action::Edit => &["e"],
+            Action::PortForward => &["f"],
+            Action::PortForwards => &["F"],
+            Action::StopPortForward => &["x", "Delete"],
+            Action::Favorite => &["*"],
+            Action::Favorites => &["b"],
+            Action::Nodes => &["N"],
+            Action::Cordon => &["c"],
+            Action::Uncordon => &["u"],
+            Action::Drain => &["D"],
+            Action::Retry => &["R"],
+            Action::DismissError => &["X"],
+            Action::Help => &["?"],
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Keymap {
+    bindings: Vec<(KeyWithModifier, Action)>,
+}
+
+impl Keymap {
+    /// Build the keymap from the defaults, overridden by the `keybind_<action>`
+    /// values of the plugin configuration. Multiple keys for one action are
+    /// separated by commas.
+    pub fn new(configuration: &BTreeMap<String, String>) -> Result<Self> {
+        for name in configuration.keys() {
+            if let Some(action) = name.strip_prefix("keybind_") {
+                if !Action::ALL.iter().any(|a| a.name() == action) {
+                    return Err(InvalidKeybinding {
+                        src: NamedSource::new("layout.kdl", name.to_owned()),
+                        bad_bit: (8, action.len()).into(),
+                    }
+                    .into());
+                }
+            }
+        }
+
+        let mut bindings: Vec<(KeyWithModifier, Action)> = vec![];
+
+        for action in Action::ALL {
+            let keys: Vec<String> = match configuration.get(&format!("keybind_{}", action.name())) {
+                Some(keys) => keys.split(',').map(|k| k.trim().to_owned()).collect(),
+                None => action
+                    .default_keys()
+                    .iter()
+                    .map(|k| k.to_string())
+                    .collect(),
+            };
+
+            for key in keys {
+                let src = format!("keybind_{} \"{}\"", action.name(), key);
+                let key_offset = src.len() - key.len() - 1;
+
+                let parsed = match KeyWithModifier::from_str(&key) {
+                    Ok(parsed) => parsed,
+                    Err(_) => {
+                        return Err(InvalidKeybinding {
+                            src: NamedSource::new("layout.kdl", src),
+                            bad_bit: (key_offset, key.len()).into(),
+                        }
+                        .into());
+                    }
+                };
+
+                if let Some((_, other)) = bindings.iter().find(|(k, _)| *k == parsed) {
+                    return Err(ConflictingKeybinding {
+                        src: NamedSource::new(
+                            "layout.kdl",
+                            format!("{} (already bound to \"{}\")", src, other.name()),
+                        ),
+                        bad_bit: (key_offset, key.len()).into(),
+                    }
+                    .into());
+                }
+
+                bindings.push((parsed, action));
+            }
+        }
+
+        Ok(Self { bindings })
+    }
+
+    pub fn action(&self, key: &KeyWithModifier) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, action)| *action)
+    }
+
+    pub fn keys(&self, action: Action) -> Vec<&KeyWithModifier> {
+        self.bindings
+            .iter()
+            .filter(|(_, a)| *a == action)
+            .map(|(key, _)| key)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(key: &str) -> KeyWithModifier {
+        KeyWithModifier::from_str(key).unwrap()
+    }
+
+    fn configuration(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn default_keys_do_not_conflict() {
+        let keymap = Keymap::new(&BTreeMap::new()).unwrap();
+
+        assert_eq!(keymap.action(&key("?")), Some(Action::Help));
+        assert_eq!(keymap.action(&key("ctrl r")), Some(Action::Refresh));
+        assert_eq!(keymap.keys(Action::Down), vec![&key("Down"), &key("j")]);
+    }
+
+    #[test]
+    fn configured_keys_replace_the_defaults() {
+        let keymap = Keymap::new(&configuration(&[("keybind_help", "F1, z")])).unwrap();
+
+        assert_eq!(keymap.keys(Action::Help), vec![&key("F1"), &key("z")]);
+        assert_eq!(keymap.action(&key("?")), None);
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected() {
+        let error = Keymap::new(&configuration(&[("keybind_unknown", "z")])).unwrap_err();
+
+        assert_eq!(error.to_string(), "Invalid keybinding");
+    }
+
+    #[test]
+    fn invalid_keys_are_rejected() {
+        let error = Keymap::new(&configuration(&[("keybind_help", "ctrl")])).unwrap_err();
+
+        assert_eq!(error.to_string(), "Invalid keybinding");
+    }
+
+    #[test]
+    fn keys_bound_twice_conflict() {
+        let error = Keymap::new(&configuration(&[("keybind_help", "j")])).unwrap_err();
+
+        assert_eq!(error.to_string(), "Conflicting keybinding");
+    }
+}
@@ -1,19 +1,135 @@
+use chrono::{DateTime, Utc};
 use itertools::Itertools;
 use miette::{Diagnostic, NamedSource, Result, SourceSpan};
-use std::collections::BTreeMap;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::{
+    cmp,
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+};
 use thiserror::Error;
 
 use zellij_tile::prelude::*;
 
+use crate::cache::{Cache, QueryResult};
+use crate::favorites::{Favorite, Favorites};
+use crate::loading::Loading;
+use crate::nodes::{node_infos, NodeInfo};
+use crate::related::related_resources;
 use crate::render::ColType;
+use crate::scheduler::Query;
+use crate::tree::owner_tree;
+
+// separates the arguments of a command, when they are kept in its context
+const ARGS_SEPARATOR: char = '\u{1f}';
 
 pub enum ListDir {
     Up,
     Down,
+    PageUp(usize),
+    PageDown(usize),
+    First,
+    Last,
+    Line(usize),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceRef {
+    pub namespace: String,
+    pub resource_type: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Resource {
+    pub name: String,
+    pub created: Option<DateTime<Utc>>,
+    pub status: String,
+    pub restarts: u64,
+    pub containers: Vec<String>,
+}
+
+/// Namespace, resource type and resource to select. Unset fields keep the
+/// current selection.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Selection {
+    pub namespace: Option<String>,
+    pub resource_type: Option<String>,
+    pub resource: Option<String>,
+}
+
+/// Content of the resource details column.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum DetailsKind {
+    #[default]
+    Yaml,
+    Tree,
+    Related,
+    Output,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortBy {
+    #[default]
+    Name,
+    Age,
+    Status,
+    Restarts,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ResourceSort {
+    pub by: SortBy,
+    pub descending: bool,
+}
+
+/// CPU and memory usage of a pod or node as reported by `kubectl top`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Metrics {
+    pub cpu: String,
+    pub memory: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ContextInfo {
+    pub name: String,
+    pub server: String,
+    pub user: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct PortForward {
+    pub id: usize,
+    pub resource_ref: ResourceRef,
+    pub ports: String,
+    pub pane_id: Option<u32>,
+    pub exit_code: Option<i32>,
+    pub exited: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub enum ApplyStrategy {
+    #[default]
+    Apply,
+    Replace,
 }
 
 #[derive(Default)]
 pub struct State {
+    // wrap around to the other end, when moving past the first or last item
+    pub wrap_around: bool,
+
+    // kubectl config view --minify
+    pub context_info: Option<ContextInfo>,
+
+    // kubectl config get-contexts
+    pub contexts: Option<Vec<String>>,
+
+    // kubectl api-resources, alias and kind of every resource type
+    pub resource_aliases: Option<Vec<(String, String)>>,
+
     // kubectl get namespace
     pub namespaces: Option<Vec<String>>,
     pub selected_namespace: Option<usize>,
@@ -25,14 +141,63 @@ pub struct State {
     pub refresh_resource_types: bool,
 
     // kubectl get <resource_type>
-    pub resources: Option<Vec<String>>,
+    pub resources: Option<Vec<Resource>>,
     pub selected_resource: Option<usize>,
     pub refresh_resources: bool,
+    pub resource_sort: ResourceSort,
+
+    // queries in flight and timing of the results per column
+    pub loading: Loading,
+
+    // results of the queries for namespaces, resource types and resources
+    pub cache: Cache,
+
+    // kubectl top pod, per resource name
+    pub metrics: BTreeMap<String, Metrics>,
+    pub metrics_unavailable: bool,
 
     // kubectl get <resource_type>/<resource>
     pub resource_details: Option<Vec<String>>,
     pub selected_resource_details_line: Option<usize>,
     pub refresh_resource_details: bool,
+    pub resource_details_ref: Option<ResourceRef>,
+    pub resource_details_kind: DetailsKind,
+    pub resource_details_offset: usize,
+    pub wrap_resource_details: bool,
+
+    // resources related to the one in the details, listed in the details
+    pub related_resources: Vec<ResourceRef>,
+
+    // selection and details position, that are applied as soon as the
+    // lists and details are loaded
+    pending_selection: Option<Selection>,
+    pending_details_position: Option<(usize, usize)>,
+
+    // starred namespaces and resources, pinned at the top of their columns
+    pub favorites: Favorites,
+    pub selected_favorite: Option<usize>,
+
+    // kubectl get nodes,pods --all-namespaces
+    pub nodes: Option<Vec<NodeInfo>>,
+
+    // kubectl top node, per node name
+    pub node_metrics: BTreeMap<String, Metrics>,
+    pub selected_node: Option<usize>,
+
+    // summary of the last cordon, uncordon or drain
+    pub node_action_output: Option<String>,
+
+    // kubectl get <resource_type>/<resource> -o jsonpath=<ports>
+    pub suggested_ports: Option<(ResourceRef, Vec<String>)>,
+
+    // kubectl port-forward running in background panes
+    pub port_forwards: Vec<PortForward>,
+    pub selected_port_forward: Option<usize>,
+    next_port_forward_id: usize,
+
+    // ids of port forwards stopped before their pane was opened, whose pane
+    // is closed as soon as it opens
+    stopping_port_forwards: BTreeSet<usize>,
 }
 
 #[derive(Error, Debug, Diagnostic)]
@@ -46,105 +211,276 @@ struct WrongExitCode {
     bad_bit: SourceSpan,
 }
 
+#[derive(Error, Debug, Diagnostic)]
+#[error("Invalid configuration")]
+#[diagnostic(help("Configuration value is not supported"))]
+struct InvalidConfigValue {
+    #[source_code]
+    src: NamedSource<String>,
+
+    #[label("This error occured")]
+    bad_bit: SourceSpan,
+}
+
+impl Resource {
+    pub(crate) fn from_json(item: &Value) -> Self {
+        let status = &item["status"];
+
+        // a waiting container, e.g. in CrashLoopBackOff, tells more than the
+        // phase of the pod
+        let waiting_reason = status["containerStatuses"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .find_map(|c| c["state"]["waiting"]["reason"].as_str());
+
+        let status_text = match (waiting_reason, status["phase"].as_str()) {
+            (Some(reason), _) => reason.to_owned(),
+            (None, Some(phase)) => phase.to_owned(),
+            (None, None) => match item["spec"]["replicas"].as_u64() {
+                Some(replicas) => format!(
+                    "{}/{}",
+                    status["readyReplicas"].as_u64().unwrap_or(0),
+                    replicas
+                ),
+                None => String::new(),
+            },
+        };
+
+        Self {
+            name: item["metadata"]["name"]
+                .as_str()
+                .unwrap_or_default()
+                .to_owned(),
+            created: item["metadata"]["creationTimestamp"]
+                .as_str()
+                .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+                .map(|t| t.with_timezone(&Utc)),
+            status: status_text,
+            restarts: status["containerStatuses"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|c| c["restartCount"].as_u64())
+                .sum(),
+            // pods list their containers in the spec, workloads in the
+            // spec of their pod template
+            containers: item["spec"]["containers"]
+                .as_array()
+                .or(item["spec"]["template"]["spec"]["containers"].as_array())
+                .into_iter()
+                .flatten()
+                .filter_map(|c| c["name"].as_str())
+                .map(|c| c.to_owned())
+                .collect(),
+        }
+    }
+
+    pub fn age(&self) -> String {
+        let created = match self.created {
+            Some(created) => created,
+            None => return String::new(),
+        };
+
+        let seconds = (Utc::now() - created).num_seconds().max(0);
+        match seconds {
+            s if s >= 86400 => format!("{}d", s / 86400),
+            s if s >= 3600 => format!("{}h", s / 3600),
+            s if s >= 60 => format!("{}m", s / 60),
+            s => format!("{}s", s),
+        }
+    }
+
+    /// Name of the resource with the value it is sorted by.
+    pub fn label(&self, sort: &ResourceSort) -> String {
+        match sort.by {
+            SortBy::Name => self.name.clone(),
+            SortBy::Age => format!("{} ({})", self.name, self.age()),
+            SortBy::Status => format!("{} ({})", self.name, self.status),
+            SortBy::Restarts => format!("{} ({})", self.name, self.restarts),
+        }
+    }
+}
+
+impl SortBy {
+    fn next(&self) -> Self {
+        match self {
+            Self::Name => Self::Age,
+            Self::Age => Self::Status,
+            Self::Status => Self::Restarts,
+            Self::Restarts => Self::Name,
+        }
+    }
+}
+
+impl fmt::Display for ResourceSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let by = match self.by {
+            SortBy::Name => "name",
+            SortBy::Age => "age",
+            SortBy::Status => "status",
+            SortBy::Restarts => "restarts",
+        };
+
+        write!(f, "{} {}", by, if self.descending { "↓" } else { "↑" })
+    }
+}
+
+impl From<&ResourceRef> for Selection {
+    fn from(resource_ref: &ResourceRef) -> Self {
+        Self {
+            namespace: Some(resource_ref.namespace.clone()),
+            resource_type: Some(resource_ref.resource_type.clone()),
+            resource: Some(resource_ref.name.clone()),
+        }
+    }
+}
+
+impl ResourceRef {
+    fn from_context(context: &BTreeMap<String, String>) -> Self {
+        Self {
+            namespace: context.get("namespace").cloned().unwrap_or_default(),
+            resource_type: context.get("resource_type").cloned().unwrap_or_default(),
+            name: context.get("resource").cloned().unwrap_or_default(),
+        }
+    }
+
+    fn command_context(&self, command: &str) -> BTreeMap<String, String> {
+        BTreeMap::from([
+            ("command".to_owned(), command.to_owned()),
+            ("namespace".to_owned(), self.namespace.clone()),
+            ("resource_type".to_owned(), self.resource_type.clone()),
+            ("resource".to_owned(), self.name.clone()),
+        ])
+    }
+}
+
+impl ApplyStrategy {
+    pub fn new(strategy: Option<&str>) -> Result<Self> {
+        match strategy {
+            None | Some("apply") => Ok(Self::Apply),
+            Some("replace") => Ok(Self::Replace),
+            Some(s) => Err(InvalidConfigValue {
+                src: NamedSource::new(
+                    "layout.kdl",
+                    format!(
+                        "\"edit_apply_strategy\" must be \"apply\" or \"replace\", got \"{}\"",
+                        s
+                    ),
+                ),
+                bad_bit: (0, 0).into(),
+            }
+            .into()),
+        }
+    }
+
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::Apply => "apply",
+            Self::Replace => "replace",
+        }
+    }
+}
+
+/// Query the name, server and user of the given context or the current
+/// context of the kubeconfig, if none is given.
+pub fn query_context_info(kube_context: &Option<&str>) {
+    let command_ctx: BTreeMap<String, String> =
+        BTreeMap::from([("command".to_owned(), "query_context_info".to_owned())]);
+
+    run_kubectl(
+        kube_context,
+        &[
+            "config",
+            "view",
+            "--minify",
+            "--output",
+            "jsonpath={.current-context}{\"\\n\"}{.clusters[0].cluster.server}{\"\\n\"}{.contexts[0].context.user}",
+        ],
+        command_ctx,
+    );
+}
+
+/// Query the names of all contexts in the kubeconfig.
+pub fn query_contexts() {
+    let command_ctx: BTreeMap<String, String> =
+        BTreeMap::from([("command".to_owned(), "query_contexts".to_owned())]);
+
+    run_kubectl(
+        &None,
+        &["config", "get-contexts", "--output", "name"],
+        command_ctx,
+    );
+}
+
+/// Query the plural names and short names of all resource types, which
+/// are used as aliases in the command prompt.
+pub fn query_api_resources(kube_context: &Option<&str>) {
+    let command_ctx: BTreeMap<String, String> =
+        BTreeMap::from([("command".to_owned(), "query_api_resources".to_owned())]);
+
+    run_kubectl(
+        kube_context,
+        &["api-resources", "--no-headers"],
+        command_ctx,
+    );
+}
+
 pub fn query_namespaces(kube_context: Option<&str>) {
     let command_ctx: BTreeMap<String, String> =
         BTreeMap::from([("command".to_owned(), "query_namespaces".to_owned())]);
 
-    if let Some(context) = kube_context {
-        run_command(
-            &[
-                "kubectl",
-                "get",
-                "namespaces",
-                "--context",
-                context,
-                "--output",
-                "jsonpath={.items[*].metadata.name}",
-            ],
-            command_ctx,
-        );
-    } else {
-        run_command(
-            &[
-                "kubectl",
-                "get",
-                "namespaces",
-                "--output",
-                "jsonpath={.items[*].metadata.name}",
-            ],
-            command_ctx,
-        );
-    }
+    run_kubectl(
+        &kube_context,
+        &[
+            "get",
+            "namespaces",
+            "--output",
+            "jsonpath={.items[*].metadata.name}",
+        ],
+        command_ctx,
+    );
 }
 
 pub fn query_resource_types(kube_context: &Option<&str>, namespace: &str) {
-    let command_ctx: BTreeMap<String, String> =
-        BTreeMap::from([("command".to_owned(), "query_resource_types".to_owned())]);
+    let command_ctx: BTreeMap<String, String> = BTreeMap::from([
+        ("command".to_owned(), "query_resource_types".to_owned()),
+        ("namespace".to_owned(), namespace.to_owned()),
+    ]);
 
-    if let Some(context) = kube_context {
-        run_command(
-            &[
-                "kubectl",
-                "get",
-                "all,ConfigMap,Endpoints,LimitRange,PersistentVolumeClaim,PersistentVolume,Pod,ReplicationController,ResourceQuota,Secret,Service,ServiceAccount",
-                "--context",
-                context,
-                "--namespace",
-                namespace,
-                "--output",
-                "jsonpath={.items[*].kind}",
-            ],
-            command_ctx,
-        );
-    } else {
-        run_command(
-            &[
-                "kubectl",
-                "get",
-                "all,ConfigMap,Endpoints,LimitRange,PersistentVolumeClaim,PersistentVolume,Pod,ReplicationController,ResourceQuota,Secret,Service,ServiceAccount",
-                "--namespace",
-                namespace,
-                "--output",
-                "jsonpath={.items[*].kind}",
-            ],
-            command_ctx,
-        );
-    }
+    run_kubectl(
+        kube_context,
+        &[
+            "get",
+            "all,ConfigMap,Endpoints,LimitRange,PersistentVolumeClaim,PersistentVolume,Pod,ReplicationController,ResourceQuota,Secret,Service,ServiceAccount",
+            "--namespace",
+            namespace,
+            "--output",
+            "jsonpath={.items[*].kind}",
+        ],
+        command_ctx,
+    );
 }
 
 pub fn query_resources(kube_context: &Option<&str>, namespace: &str, resource_type: &str) {
-    let command_ctx: BTreeMap<String, String> =
-        BTreeMap::from([("command".to_owned(), "query_resources".to_owned())]);
+    let command_ctx: BTreeMap<String, String> = BTreeMap::from([
+        ("command".to_owned(), "query_resources".to_owned()),
+        ("namespace".to_owned(), namespace.to_owned()),
+        ("resource_type".to_owned(), resource_type.to_owned()),
+    ]);
 
-    if let Some(context) = kube_context {
-        run_command(
-            &[
-                "kubectl",
-                "get",
-                resource_type,
-                "--context",
-                context,
-                "--namespace",
-                namespace,
-                "--output",
-                "jsonpath={.items[*].metadata.name}",
-            ],
-            command_ctx,
-        );
-    } else {
-        run_command(
-            &[
-                "kubectl",
-                "get",
-                resource_type,
-                "--namespace",
-                namespace,
-                "--output",
-                "jsonpath={.items[*].metadata.name}",
-            ],
-            command_ctx,
-        );
-    }
+    run_kubectl(
+        kube_context,
+        &[
+            "get",
+            resource_type,
+            "--namespace",
+            namespace,
+            "--output",
+            "json",
+        ],
+        command_ctx,
+    );
 }
 
 pub fn query_resource_details(
@@ -152,63 +488,874 @@ pub fn query_resource_details(
     namespace: &str,
     resource_type: &str,
     resource: &str,
+    request_id: u64,
+) {
+    let command_ctx: BTreeMap<String, String> = BTreeMap::from([
+        ("command".to_owned(), "query_resource_details".to_owned()),
+        ("namespace".to_owned(), namespace.to_owned()),
+        ("resource_type".to_owned(), resource_type.to_owned()),
+        ("resource".to_owned(), resource.to_owned()),
+        ("request_id".to_owned(), request_id.to_string()),
+    ]);
+
+    run_kubectl(
+        &kube_context,
+        &[
+            "get",
+            resource_type,
+            resource,
+            "--namespace",
+            namespace,
+            "--output",
+            "yaml",
+        ],
+        command_ctx,
+    );
+}
+
+pub fn diff_resource(kube_context: &Option<&str>, path: &str, file: &str) {
+    let command_ctx: BTreeMap<String, String> = BTreeMap::from([
+        ("command".to_owned(), "diff_resource".to_owned()),
+        ("path".to_owned(), path.to_owned()),
+        ("file".to_owned(), file.to_owned()),
+    ]);
+
+    run_kubectl(kube_context, &["diff", "--filename", path], command_ctx);
+}
+
+pub fn apply_resource(
+    kube_context: &Option<&str>,
+    path: &str,
+    file: &str,
+    strategy: &ApplyStrategy,
 ) {
+    let command_ctx: BTreeMap<String, String> = BTreeMap::from([
+        ("command".to_owned(), "apply_resource".to_owned()),
+        ("path".to_owned(), path.to_owned()),
+        ("file".to_owned(), file.to_owned()),
+        ("strategy".to_owned(), strategy.as_str().to_owned()),
+    ]);
+
+    run_kubectl(
+        kube_context,
+        &[strategy.as_str(), "--filename", path],
+        command_ctx,
+    );
+}
+
+/// Query the ports exposed by a pod or service, such that they can be
+/// suggested when starting a port forward.
+pub fn query_ports(kube_context: &Option<&str>, resource_ref: &ResourceRef) {
+    let jsonpath = match resource_ref.resource_type.as_str() {
+        "Service" => "jsonpath={.spec.ports[*].port}",
+        _ => "jsonpath={.spec.containers[*].ports[*].containerPort}",
+    };
+
+    run_kubectl(
+        kube_context,
+        &[
+            "get",
+            &resource_ref.resource_type,
+            &resource_ref.name,
+            "--namespace",
+            &resource_ref.namespace,
+            "--output",
+            jsonpath,
+        ],
+        resource_ref.command_context("query_ports"),
+    );
+}
+
+/// Query all workloads, pods, services and PVCs in the namespace of the
+/// resource, such that its owner tree can be built.
+pub fn query_owner_tree(kube_context: &Option<&str>, resource_ref: &ResourceRef, request_id: u64) {
+    let mut command_ctx = resource_ref.command_context("query_owner_tree");
+    command_ctx.insert("request_id".to_owned(), request_id.to_string());
+
+    run_kubectl(
+        kube_context,
+        &[
+            "get",
+            "deployments,replicasets,statefulsets,daemonsets,cronjobs,jobs,pods,services,persistentvolumeclaims",
+            "--namespace",
+            &resource_ref.namespace,
+            "--output",
+            "json",
+        ],
+        command_ctx,
+    );
+}
+
+/// Query the pods, services, ingresses and endpoints in the namespace of the
+/// resource, such that its related resources can be collected.
+pub fn query_related(kube_context: &Option<&str>, resource_ref: &ResourceRef, request_id: u64) {
+    let mut command_ctx = resource_ref.command_context("query_related");
+    command_ctx.insert("request_id".to_owned(), request_id.to_string());
+
+    run_kubectl(
+        kube_context,
+        &[
+            "get",
+            "pods,services,ingresses,endpoints",
+            "--namespace",
+            &resource_ref.namespace,
+            "--output",
+            "json",
+        ],
+        command_ctx,
+    );
+}
+
+/// Run the command of a custom action and capture its output for the
+/// details column.
+pub fn run_custom_action(name: &str, args: &[String], resource_ref: &ResourceRef, request_id: u64) {
+    let mut command_ctx = resource_ref.command_context("custom_action");
+    command_ctx.insert("action".to_owned(), name.to_owned());
+    command_ctx.insert("request_id".to_owned(), request_id.to_string());
+
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    run_with_args(&args, command_ctx);
+}
+
+/// Query the CPU and memory usage of the pods in the namespace. This
+/// requires the metrics-server in the cluster.
+pub fn query_metrics(kube_context: &Option<&str>, namespace: &str, resource_type: &str) {
+    let command_ctx: BTreeMap<String, String> = BTreeMap::from([
+        ("command".to_owned(), "query_metrics".to_owned()),
+        ("namespace".to_owned(), namespace.to_owned()),
+        ("resource_type".to_owned(), resource_type.to_owned()),
+    ]);
+
+    run_kubectl(
+        kube_context,
+        &[
+            "top",
+            &resource_type.to_lowercase(),
+            "--namespace",
+            namespace,
+            "--no-headers",
+        ],
+        command_ctx,
+    );
+}
+
+/// Query the CPU and memory usage of all nodes for the nodes view. This
+/// requires the metrics-server in the cluster.
+pub fn query_node_metrics(kube_context: &Option<&str>) {
+    let command_ctx: BTreeMap<String, String> =
+        BTreeMap::from([("command".to_owned(), "query_node_metrics".to_owned())]);
+
+    run_kubectl(kube_context, &["top", "node", "--no-headers"], command_ctx);
+}
+
+/// Query all nodes with the pods scheduled to them, such that requested
+/// resources and pod counts can be summed up per node.
+pub fn query_nodes(kube_context: &Option<&str>) {
     let command_ctx: BTreeMap<String, String> =
-        BTreeMap::from([("command".to_owned(), "query_resource_details".to_owned())]);
+        BTreeMap::from([("command".to_owned(), "query_nodes".to_owned())]);
+
+    run_kubectl(
+        kube_context,
+        &["get", "nodes,pods", "--all-namespaces", "--output", "json"],
+        command_ctx,
+    );
+}
+
+/// Run `kubectl cordon`, `uncordon` or `drain` for the node.
+pub fn run_node_action(kube_context: &Option<&str>, action: &str, node: &str, options: &[String]) {
+    let command_ctx: BTreeMap<String, String> = BTreeMap::from([
+        ("command".to_owned(), "node_action".to_owned()),
+        ("action".to_owned(), action.to_owned()),
+        ("node".to_owned(), node.to_owned()),
+    ]);
+
+    let mut args = vec![action, node];
+    args.extend(options.iter().map(|o| o.as_str()));
+
+    run_kubectl(kube_context, &args, command_ctx);
+}
+
+pub fn supports_metrics(resource_type: &str) -> bool {
+    resource_type == "Pod"
+}
+
+pub fn supports_port_forward(resource_type: &str) -> bool {
+    matches!(resource_type, "Pod" | "Service")
+}
+
+/// Parse the columns of `kubectl api-resources --no-headers` into aliases
+/// for the kinds. The short names column is empty for many resource types.
+fn parse_api_resources(output: &str) -> Vec<(String, String)> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+
+            match columns.as_slice() {
+                [name, short_names, _, _, kind] => Some((*name, Some(*short_names), *kind)),
+                [name, _, _, kind] => Some((*name, None, *kind)),
+                _ => None,
+            }
+        })
+        .flat_map(|(name, short_names, kind)| {
+            short_names
+                .into_iter()
+                .flat_map(|s| s.split(','))
+                .chain([name])
+                .map(move |alias| (alias.to_owned(), kind.to_owned()))
+        })
+        .collect()
+}
+
+/// Parse the columns of `kubectl top pod --no-headers` (name, CPU, memory)
+/// or `kubectl top node --no-headers`, which adds the relative usage.
+fn parse_metrics(output: &str) -> BTreeMap<String, Metrics> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let columns: Vec<&str> = line.split_whitespace().collect();
+
+            match columns.as_slice() {
+                [name, cpu, memory] => Some((
+                    name.to_string(),
+                    Metrics {
+                        cpu: cpu.to_string(),
+                        memory: memory.to_string(),
+                    },
+                )),
+                [name, cpu, cpu_percent, memory, memory_percent] => Some((
+                    name.to_string(),
+                    Metrics {
+                        cpu: format!("{} {}", cpu, cpu_percent),
+                        memory: format!("{} {}", memory, memory_percent),
+                    },
+                )),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+fn run_kubectl(
+    kube_context: &Option<&str>,
+    args: &[&str],
+    mut command_ctx: BTreeMap<String, String>,
+) {
+    let mut command = vec!["kubectl"];
+    command.extend_from_slice(args);
 
     if let Some(context) = kube_context {
-        run_command(
-            &[
-                "kubectl",
-                "get",
-                resource_type,
-                resource,
-                "--context",
-                context,
-                "--namespace",
-                namespace,
-                "--output",
-                "yaml",
-            ],
-            command_ctx,
-        );
-    } else {
-        run_command(
-            &[
-                "kubectl",
-                "get",
-                resource_type,
-                resource,
-                "--namespace",
-                namespace,
-                "--output",
-                "yaml",
-            ],
-            command_ctx,
-        );
+        command.extend_from_slice(&["--context", context]);
+        command_ctx.insert("kube_context".to_owned(), context.to_string());
+    }
+
+    run_with_args(&command, command_ctx);
+}
+
+/// Run the command and keep its arguments in the context, such that it can
+/// be retried after a failure.
+fn run_with_args(command: &[&str], mut command_ctx: BTreeMap<String, String>) {
+    command_ctx.insert("args".to_owned(), command.join(&ARGS_SEPARATOR.to_string()));
+
+    run_command(command, command_ctx);
+}
+
+/// Run a failed command again with the arguments and context it was run
+/// with. Commands without arguments in the context are not retried.
+pub fn retry(command_ctx: &BTreeMap<String, String>) {
+    if let Some(args) = command_ctx.get("args") {
+        let args: Vec<&str> = args.split(ARGS_SEPARATOR).collect();
+        run_command(&args, command_ctx.clone());
     }
 }
 
 impl State {
+    /// Start `kubectl port-forward` in a suppressed pane. The pane id is
+    /// assigned as soon as zellij reports the pane as opened.
+    pub fn start_port_forward(
+        &mut self,
+        kube_context: &Option<&str>,
+        resource_ref: ResourceRef,
+        ports: &str,
+    ) {
+        let id = self.next_port_forward_id;
+        self.next_port_forward_id += 1;
+
+        let mut args = vec![
+            "port-forward".to_owned(),
+            "--namespace".to_owned(),
+            resource_ref.namespace.clone(),
+            format!(
+                "{}/{}",
+                resource_ref.resource_type.to_lowercase(),
+                resource_ref.name
+            ),
+            ports.to_owned(),
+        ];
+        if let Some(context) = kube_context {
+            args.extend(["--context".to_owned(), context.to_string()]);
+        }
+
+        open_command_pane_background(
+            CommandToRun {
+                path: "kubectl".into(),
+                args,
+                cwd: None,
+            },
+            BTreeMap::from([
+                ("command".to_owned(), "port_forward".to_owned()),
+                ("port_forward_id".to_owned(), id.to_string()),
+            ]),
+        );
+
+        self.port_forwards.push(PortForward {
+            id,
+            resource_ref,
+            ports: ports.to_owned(),
+            pane_id: None,
+            exit_code: None,
+            exited: false,
+        });
+        self.selected_port_forward = Some(self.port_forwards.len() - 1);
+    }
+
+    pub fn port_forward_opened(&mut self, pane_id: u32, context: &BTreeMap<String, String>) {
+        let stopping = context
+            .get("port_forward_id")
+            .and_then(|id| id.parse().ok())
+            .is_some_and(|id| self.stopping_port_forwards.remove(&id));
+        if stopping {
+            close_terminal_pane(pane_id);
+            return;
+        }
+
+        if let Some(port_forward) = self.port_forward_by_context(context) {
+            port_forward.pane_id = Some(pane_id);
+        }
+    }
+
+    pub fn port_forward_exited(
+        &mut self,
+        exit_code: Option<i32>,
+        context: &BTreeMap<String, String>,
+    ) {
+        if let Some(port_forward) = self.port_forward_by_context(context) {
+            port_forward.exit_code = exit_code;
+            port_forward.exited = true;
+        }
+    }
+
+    pub fn stop_selected_port_forward(&mut self) {
+        let index = match self.selected_port_forward {
+            Some(index) if index < self.port_forwards.len() => index,
+            _ => return,
+        };
+
+        let port_forward = self.port_forwards.remove(index);
+        match port_forward.pane_id {
+            Some(pane_id) => close_terminal_pane(pane_id),
+            None => {
+                self.stopping_port_forwards.insert(port_forward.id);
+            }
+        }
+
+        self.selected_port_forward = match self.port_forwards.len() {
+            0 => None,
+            len => Some(index.min(len - 1)),
+        };
+    }
+
+    pub fn select_port_forward(&mut self, direction: ListDir) {
+        if self.port_forwards.is_empty() {
+            return;
+        }
+
+        self.selected_port_forward = Some(get_next_item(
+            &self.port_forwards,
+            &self.selected_port_forward,
+            direction,
+            self.wrap_around,
+        ));
+    }
+
+    pub fn select_node(&mut self, direction: ListDir) {
+        let nodes = match &self.nodes {
+            Some(nodes) if !nodes.is_empty() => nodes,
+            _ => return,
+        };
+
+        self.selected_node = Some(get_next_item(
+            nodes,
+            &self.selected_node,
+            direction,
+            self.wrap_around,
+        ));
+    }
+
+    pub fn get_selected_node(&self) -> Option<&NodeInfo> {
+        self.nodes.as_ref()?.get(self.selected_node?)
+    }
+
+    fn port_forward_by_context(
+        &mut self,
+        context: &BTreeMap<String, String>,
+    ) -> Option<&mut PortForward> {
+        let id: usize = context.get("port_forward_id")?.parse().ok()?;
+
+        self.port_forwards.iter_mut().find(|p| p.id == id)
+    }
+
+    /// Scroll the resource details horizontally by `columns`, but not past
+    /// the longest line.
+    pub fn scroll_resource_details(&mut self, columns: isize) {
+        if self.wrap_resource_details {
+            return;
+        }
+
+        let max_offset = match &self.resource_details {
+            Some(lines) => lines
+                .iter()
+                .map(|l| l.chars().count())
+                .max()
+                .unwrap_or(0)
+                .saturating_sub(1),
+            None => 0,
+        };
+
+        self.resource_details_offset = cmp::min(
+            self.resource_details_offset.saturating_add_signed(columns),
+            max_offset,
+        );
+    }
+
+    /// Cycle the field the resources are sorted by, keeping the selected
+    /// resource selected.
+    pub fn cycle_resource_sort(&mut self) {
+        self.resource_sort.by = self.resource_sort.by.next();
+        self.resource_sort.descending = false;
+        self.resort_resources();
+    }
+
+    pub fn reverse_resource_sort(&mut self) {
+        self.resource_sort.descending = !self.resource_sort.descending;
+        self.resort_resources();
+    }
+
+    fn resort_resources(&mut self) {
+        let selected = self.get_selected_item(&ColType::Resource);
+        let favorites = self.resource_favorites();
+
+        if let Some(resources) = &mut self.resources {
+            sort_resources(resources, &self.resource_sort);
+            resources.sort_by_key(|r| !favorites.contains(&r.name));
+
+            self.selected_resource = selected
+                .and_then(|name| resources.iter().position(|r| r.name == name))
+                .or(self.selected_resource);
+        }
+    }
+
+    /// Name of the context the cluster state was queried from.
+    pub fn context_name(&self) -> &str {
+        self.context_info
+            .as_ref()
+            .map(|c| c.name.as_str())
+            .unwrap_or_default()
+    }
+
+    pub fn is_favorite_namespace(&self, namespace: &str) -> bool {
+        self.favorites.contains(&Favorite::Namespace {
+            context: self.context_name().to_owned(),
+            namespace: namespace.to_owned(),
+        })
+    }
+
+    /// Names of the starred resources of the selected namespace and type.
+    pub fn resource_favorites(&self) -> Vec<String> {
+        let (namespace, resource_type) = match (
+            self.get_selected_item(&ColType::Namespace),
+            self.get_selected_item(&ColType::ResourceType),
+        ) {
+            (Some(namespace), Some(resource_type)) => (namespace, resource_type),
+            _ => return vec![],
+        };
+
+        self.favorites
+            .items()
+            .iter()
+            .filter_map(|f| match f {
+                Favorite::Resource {
+                    context,
+                    resource_ref,
+                } if context == self.context_name()
+                    && resource_ref.namespace == namespace
+                    && resource_ref.resource_type == resource_type =>
+                {
+                    Some(resource_ref.name.clone())
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Move the starred namespaces and resources to the top of their
+    /// columns, keeping the selected items.
+    fn pin_favorites(&mut self) {
+        let selected = self.get_selected_item(&ColType::Namespace);
+        let favorites: Vec<String> = self
+            .namespaces
+            .iter()
+            .flatten()
+            .filter(|n| self.is_favorite_namespace(n))
+            .cloned()
+            .collect();
+
+        if let Some(namespaces) = &mut self.namespaces {
+            namespaces.sort_by_key(|n| !favorites.contains(n));
+
+            self.selected_namespace = selected
+                .and_then(|name| namespaces.iter().position(|n| *n == name))
+                .or(self.selected_namespace);
+        }
+
+        self.resort_resources();
+    }
+
+    /// Star or unstar the selected namespace or resource.
+    pub fn toggle_favorite(&mut self, col_type: &ColType) -> Result<(), String> {
+        let context = self.context_name().to_owned();
+
+        let favorite = match col_type {
+            ColType::Namespace => match self.get_selected_item(&ColType::Namespace) {
+                Some(namespace) => Favorite::Namespace { context, namespace },
+                None => return Ok(()),
+            },
+            ColType::Resource => match (
+                self.get_selected_item(&ColType::Namespace),
+                self.get_selected_item(&ColType::ResourceType),
+                self.get_selected_item(&ColType::Resource),
+            ) {
+                (Some(namespace), Some(resource_type), Some(name)) => Favorite::Resource {
+                    context,
+                    resource_ref: ResourceRef {
+                        namespace,
+                        resource_type,
+                        name,
+                    },
+                },
+                _ => return Ok(()),
+            },
+            _ => return Ok(()),
+        };
+
+        let result = self.favorites.toggle(favorite);
+        self.pin_favorites();
+
+        result
+    }
+
+    /// Unstar the favorite selected in the favorites view.
+    pub fn remove_selected_favorite(&mut self) -> Result<(), String> {
+        let favorite = match self
+            .selected_favorite
+            .and_then(|index| self.favorites.items().get(index))
+        {
+            Some(favorite) => favorite.clone(),
+            None => return Ok(()),
+        };
+
+        let result = self.favorites.toggle(favorite);
+        self.pin_favorites();
+
+        let count = self.favorites.items().len();
+        self.selected_favorite =
+            (count > 0).then(|| self.selected_favorite.unwrap_or_default().min(count - 1));
+
+        result
+    }
+
+    pub fn select_favorite(&mut self, direction: ListDir) {
+        if self.favorites.items().is_empty() {
+            return;
+        }
+
+        self.selected_favorite = Some(get_next_item(
+            self.favorites.items(),
+            &self.selected_favorite,
+            direction,
+            self.wrap_around,
+        ));
+    }
+
+    pub fn get_selected_favorite(&self) -> Option<&Favorite> {
+        self.favorites.items().get(self.selected_favorite?)
+    }
+
+    /// Select the namespace, resource type and resource. Lists, that must be
+    /// reloaded for the new selection, continue it once they are loaded.
+    pub fn select(&mut self, selection: Selection) {
+        self.pending_selection = Some(selection);
+        self.apply_pending_selection();
+    }
+
+    pub fn has_pending_selection(&self) -> bool {
+        self.pending_selection.is_some()
+    }
+
+    fn apply_pending_selection(&mut self) {
+        let selection = match &self.pending_selection {
+            Some(selection) => selection.clone(),
+            None => return,
+        };
+
+        if let Some(namespace) = &selection.namespace {
+            let namespaces = match &self.namespaces {
+                Some(namespaces) => namespaces,
+                None => return,
+            };
+
+            match namespaces.iter().position(|n| n == namespace) {
+                Some(index) if self.selected_namespace != Some(index) => {
+                    self.selected_namespace = Some(index);
+                    self.refresh_resource_types = true;
+                    return;
+                }
+                Some(_) => (),
+                None => {
+                    self.pending_selection = None;
+                    return;
+                }
+            }
+        }
+
+        if self.refresh_resource_types {
+            return;
+        }
+
+        if let Some(resource_type) = &selection.resource_type {
+            let resource_types = match &self.resource_types {
+                Some(resource_types) => resource_types,
+                None => return,
+            };
+
+            match resource_types.iter().position(|t| t == resource_type) {
+                Some(index) if self.selected_resource_type != Some(index) => {
+                    self.selected_resource_type = Some(index);
+                    self.refresh_resources = true;
+                    return;
+                }
+                Some(_) => (),
+                None => {
+                    self.pending_selection = None;
+                    return;
+                }
+            }
+        }
+
+        if self.refresh_resources {
+            return;
+        }
+
+        if let (Some(resource), Some(resources)) = (&selection.resource, &self.resources) {
+            if let Some(index) = resources.iter().position(|r| r.name == *resource) {
+                self.selected_resource = Some(index);
+            }
+        }
+
+        self.pending_selection = None;
+    }
+
+    /// Show the cached result of the query. Returns None, when nothing is
+    /// cached, otherwise whether the result is still fresh.
+    pub fn load_cached(&mut self, kube_context: &str, query: &Query) -> Option<bool> {
+        let (result, queried, fresh) = self.cache.get(kube_context, query)?;
+        let result = result.clone();
+
+        self.apply_query_result(query, result);
+        self.loading.cached(query.command(), queried);
+
+        Some(fresh)
+    }
+
+    /// Show the list of a column. When the column waits for it, the first
+    /// item is selected and the next column is loaded. Otherwise it is a
+    /// refresh, that keeps the selected item. Results for namespaces or
+    /// resource types, that are no longer selected, are ignored.
+    fn apply_query_result(&mut self, query: &Query, result: QueryResult) {
+        let selected_namespace = self.get_selected_item(&ColType::Namespace);
+        let selected_resource_type = self.get_selected_item(&ColType::ResourceType);
+
+        match (query, result) {
+            (Query::Namespaces, QueryResult::Namespaces(namespaces)) => {
+                let waiting = self.namespaces.is_none();
+
+                self.namespaces = Some(namespaces);
+                self.selected_namespace = selected_namespace
+                    .filter(|_| !waiting)
+                    .and_then(|name| self.namespaces.iter().flatten().position(|n| *n == name));
+                self.pin_favorites();
+
+                if self.selected_namespace.is_none() {
+                    self.selected_namespace = Some(0);
+                    self.refresh_resource_types = true;
+                }
+                self.refresh_namespaces = false;
+            }
+            (Query::ResourceTypes { namespace }, QueryResult::ResourceTypes(resource_types)) => {
+                if selected_namespace.as_ref() != Some(namespace) {
+                    return;
+                }
+
+                let waiting = self.refresh_resource_types;
+
+                self.selected_resource_type = selected_resource_type
+                    .filter(|_| !waiting)
+                    .and_then(|name| resource_types.iter().position(|t| *t == name));
+                self.resource_types = Some(resource_types);
+
+                if self.selected_resource_type.is_none() {
+                    self.selected_resource_type = Some(0);
+                    self.refresh_resources = true;
+                }
+                self.refresh_resource_types = false;
+            }
+            (
+                Query::Resources {
+                    namespace,
+                    resource_type,
+                },
+                QueryResult::Resources(resources),
+            ) => {
+                if selected_namespace.as_ref() != Some(namespace)
+                    || selected_resource_type.as_ref() != Some(resource_type)
+                {
+                    return;
+                }
+
+                let waiting = self.refresh_resources;
+                let selected = self
+                    .get_selected_item(&ColType::Resource)
+                    .filter(|_| !waiting);
+
+                self.resources = Some(resources);
+                if waiting {
+                    self.metrics.clear();
+                }
+                self.selected_resource = selected
+                    .and_then(|name| self.resources.iter().flatten().position(|r| r.name == name));
+                self.resort_resources();
+
+                if self.selected_resource.is_none() {
+                    self.selected_resource = Some(0);
+                    self.refresh_resource_details = true;
+                }
+                self.refresh_resources = false;
+            }
+            _ => return,
+        }
+
+        self.apply_pending_selection();
+    }
+
+    /// Stop waiting for the result of a failed query, such that it is not
+    /// dispatched again until it is retried.
+    pub fn query_failed(&mut self, command: &str) {
+        self.loading.fail(command);
+
+        match command {
+            "query_namespaces" => {
+                self.namespaces.get_or_insert_with(Vec::new);
+                self.refresh_namespaces = false;
+            }
+            "query_resource_types" => self.refresh_resource_types = false,
+            "query_resources" => self.refresh_resources = false,
+            "query_resource_details" => self.refresh_resource_details = false,
+            _ => (),
+        }
+    }
+
+    /// Forget everything queried from the cluster, e.g. after switching the
+    /// context. Settings and port forwards are kept.
+    pub fn clear(&mut self) {
+        let state = std::mem::take(self);
+
+        *self = Self {
+            wrap_around: state.wrap_around,
+            contexts: state.contexts,
+            resource_sort: state.resource_sort,
+            wrap_resource_details: state.wrap_resource_details,
+            favorites: state.favorites,
+            selected_favorite: state.selected_favorite,
+            cache: state.cache,
+            port_forwards: state.port_forwards,
+            selected_port_forward: state.selected_port_forward,
+            next_port_forward_id: state.next_port_forward_id,
+            stopping_port_forwards: state.stopping_port_forwards,
+            ..Default::default()
+        };
+    }
+
+    /// Restore the selected line and horizontal scroll position, once the
+    /// next resource details are loaded.
+    pub fn restore_details_position(&mut self, line: usize, offset: usize) {
+        self.pending_details_position = Some((line, offset));
+    }
+
+    /// Toggle soft-wrapping of the resource details. Wrapped details are not
+    /// scrolled horizontally.
+    pub fn toggle_wrap_resource_details(&mut self) {
+        self.wrap_resource_details = !self.wrap_resource_details;
+        self.resource_details_offset = 0;
+    }
+
+    /// Whether the resource can be selected in the columns. Kinds, that are
+    /// not listed in the resource types of the selected namespace, e.g.
+    /// cluster-scoped nodes, cannot be selected.
+    pub fn can_select(&self, resource_ref: &ResourceRef) -> bool {
+        self.get_selected_item(&ColType::Namespace).as_ref() == Some(&resource_ref.namespace)
+            && self
+                .resource_types
+                .iter()
+                .flatten()
+                .any(|t| *t == resource_ref.resource_type)
+    }
+
+    /// The related resource at the selected line of the details.
+    pub fn selected_related_resource(&self) -> Option<&ResourceRef> {
+        if self.resource_details_kind != DetailsKind::Related {
+            return None;
+        }
+
+        // the first line is the title of the list
+        let line = self.selected_resource_details_line?.checked_sub(1)?;
+
+        self.related_resources.get(line)
+    }
+
+    /// The selected resource with all its fields.
+    pub fn get_selected_resource(&self) -> Option<&Resource> {
+        self.resources.as_ref()?.get(self.selected_resource?)
+    }
+
     pub fn get_selected_item(&self, col_type: &ColType) -> Option<String> {
         match col_type {
             ColType::Namespace => {
                 if let Some(namespaces) = &self.namespaces {
-                    Some(namespaces[self.selected_namespace?].clone())
+                    Some(namespaces.get(self.selected_namespace?)?.clone())
                 } else {
                     None
                 }
             }
             ColType::ResourceType => {
                 if let Some(resource_types) = &self.resource_types {
-                    Some(resource_types[self.selected_resource_type?].clone())
+                    Some(resource_types.get(self.selected_resource_type?)?.clone())
                 } else {
                     None
                 }
             }
             ColType::Resource => {
                 if let Some(resources) = &self.resources {
-                    Some(resources[self.selected_resource?].clone())
+                    Some(resources.get(self.selected_resource?)?.name.clone())
                 } else {
                     None
                 }
@@ -225,6 +1372,7 @@ impl State {
                         namespaces,
                         &self.selected_namespace,
                         direction,
+                        self.wrap_around,
                     ));
                     self.refresh_resource_types = true;
                 }
@@ -235,14 +1383,19 @@ impl State {
                         resource_types,
                         &self.selected_resource_type,
                         direction,
+                        self.wrap_around,
                     ));
                     self.refresh_resources = true;
                 }
             }
             ColType::Resource => {
                 if let Some(resources) = &self.resources {
-                    self.selected_resource =
-                        Some(get_next_item(resources, &self.selected_resource, direction));
+                    self.selected_resource = Some(get_next_item(
+                        resources,
+                        &self.selected_resource,
+                        direction,
+                        self.wrap_around,
+                    ));
                     self.refresh_resource_details = true;
                 }
             }
@@ -252,6 +1405,7 @@ impl State {
                         resource_details,
                         &self.selected_resource_details_line,
                         direction,
+                        self.wrap_around,
                     ));
                 }
             }
@@ -267,49 +1421,265 @@ impl State {
     ) -> Result<()> {
         match context.get("command") {
             Some(command) => match command.as_str() {
-                "query_namespaces" => {
-                    let result = self.result(exit_code, stdout, stderr, context, ' ')?;
+                "query_context_info" => {
+                    guard_exit_code(exit_code, stderr)?;
 
-                    self.namespaces = Some(result);
-                    self.selected_namespace = Some(0);
+                    // parse the lines without deduplication, as the context
+                    // and user name are often equal
+                    let stdout = String::from_utf8_lossy(&stdout);
+                    let mut result = stdout.lines().map(|l| l.to_owned());
 
-                    self.refresh_namespaces = false;
-                    self.refresh_resource_types = true;
+                    self.context_info = Some(ContextInfo {
+                        name: result.next().unwrap_or_default(),
+                        server: result.next().unwrap_or_default(),
+                        user: result.next().unwrap_or_default(),
+                    });
+                    self.pin_favorites();
 
                     Ok(())
                 }
-                "query_resource_types" => {
-                    let result = self.result(exit_code, stdout, stderr, context, ' ')?;
+                "query_contexts" => {
+                    let result = self.result(exit_code, stdout, stderr, context, '\n')?;
+                    self.contexts = Some(result.into_iter().filter(|c| !c.is_empty()).collect());
 
-                    self.resource_types = Some(result);
-                    self.selected_resource_type = Some(0);
+                    Ok(())
+                }
+                "query_api_resources" => {
+                    guard_exit_code(exit_code, stderr)?;
 
-                    self.refresh_resource_types = false;
-                    self.refresh_resources = true;
+                    self.resource_aliases =
+                        Some(parse_api_resources(&String::from_utf8_lossy(&stdout)));
 
                     Ok(())
                 }
-                "query_resources" => {
-                    let result = self.result(exit_code, stdout, stderr, context, ' ')?;
+                "query_namespaces" | "query_resource_types" | "query_resources" => {
+                    let query = match Query::from_context(&context) {
+                        Some(query) => query,
+                        None => return Ok(()),
+                    };
+                    let kube_context = context.get("kube_context").cloned().unwrap_or_default();
 
-                    self.resources = Some(result);
-                    self.selected_resource = Some(0);
+                    let result = match query {
+                        Query::Namespaces => QueryResult::Namespaces(
+                            self.result(exit_code, stdout, stderr, context, ' ')?,
+                        ),
+                        Query::ResourceTypes { .. } => QueryResult::ResourceTypes(
+                            self.result(exit_code, stdout, stderr, context, ' ')?,
+                        ),
+                        Query::Resources { .. } => {
+                            guard_exit_code(exit_code, stderr)?;
+                            let list = parse_json(&stdout)?;
 
-                    self.refresh_resources = false;
-                    self.refresh_resource_details = true;
+                            QueryResult::Resources(
+                                list["items"]
+                                    .as_array()
+                                    .into_iter()
+                                    .flatten()
+                                    .map(Resource::from_json)
+                                    .collect(),
+                            )
+                        }
+                    };
+
+                    self.cache
+                        .insert(&kube_context, query.clone(), result.clone());
+                    self.apply_query_result(&query, result);
+
+                    Ok(())
+                }
+                "query_nodes" => {
+                    guard_exit_code(exit_code, stderr)?;
+                    let list = parse_json(&stdout)?;
+
+                    let nodes = node_infos(&list);
+
+                    // keep the selected node, when refreshing the list
+                    let selected = self.get_selected_node().map(|n| n.name.clone());
+                    self.selected_node = match selected {
+                        Some(name) => nodes.iter().position(|n| n.name == name),
+                        None => None,
+                    }
+                    .or((!nodes.is_empty()).then_some(0));
+                    self.nodes = Some(nodes);
+
+                    Ok(())
+                }
+                "node_action" => {
+                    let action = context.get("action").cloned().unwrap_or_default();
+                    let node = context.get("node").cloned().unwrap_or_default();
+
+                    let output = output_lines(exit_code, stdout, stderr);
+                    let summary = match (exit_code, output.last()) {
+                        (Some(0), Some(line)) => line.clone(),
+                        (Some(0), None) => format!("{} {} finished", action, node),
+                        (_, Some(line)) => format!("{} {} failed: {}", action, node, line),
+                        (_, None) => format!("{} {} failed", action, node),
+                    };
+                    self.node_action_output = Some(summary);
+
+                    Ok(())
+                }
+                "query_metrics" => {
+                    // metrics are optional, the plugin works without them
+                    if exit_code != Some(0) {
+                        self.metrics.clear();
+                        self.metrics_unavailable = true;
+
+                        return Ok(());
+                    }
+
+                    // drop metrics of a namespace or type, that is no
+                    // longer selected
+                    if context.get("namespace")
+                        != self.get_selected_item(&ColType::Namespace).as_ref()
+                        || context.get("resource_type")
+                            != self.get_selected_item(&ColType::ResourceType).as_ref()
+                    {
+                        return Ok(());
+                    }
+
+                    self.metrics = parse_metrics(&String::from_utf8_lossy(&stdout));
+                    self.metrics_unavailable = false;
+
+                    Ok(())
+                }
+                "query_node_metrics" => {
+                    if exit_code != Some(0) {
+                        self.node_metrics.clear();
+                        self.metrics_unavailable = true;
+
+                        return Ok(());
+                    }
+
+                    self.node_metrics = parse_metrics(&String::from_utf8_lossy(&stdout));
+                    self.metrics_unavailable = false;
 
                     Ok(())
                 }
                 "query_resource_details" => {
+                    let resource_ref = ResourceRef::from_context(&context);
                     let result = self.result(exit_code, stdout, stderr, context, '\n')?;
 
+                    let (line, offset) = self.pending_details_position.take().unwrap_or_default();
+                    let line = line.min(result.len().saturating_sub(1));
+
                     self.resource_details = Some(result);
-                    self.selected_resource_details_line = Some(0);
+                    self.selected_resource_details_line = Some(line);
+                    self.resource_details_ref = Some(resource_ref);
+                    self.resource_details_kind = DetailsKind::Yaml;
+                    self.resource_details_offset = offset;
 
                     self.refresh_resource_details = false;
 
                     Ok(())
                 }
+                "query_owner_tree" => {
+                    let resource_ref = ResourceRef::from_context(&context);
+
+                    guard_exit_code(exit_code, stderr)?;
+                    let list = parse_json(&stdout)?;
+
+                    self.resource_details = Some(owner_tree(
+                        &list,
+                        &resource_ref.resource_type,
+                        &resource_ref.name,
+                    ));
+                    self.selected_resource_details_line = Some(0);
+                    self.resource_details_ref = Some(resource_ref);
+                    self.resource_details_kind = DetailsKind::Tree;
+                    self.resource_details_offset = 0;
+
+                    Ok(())
+                }
+                "query_related" => {
+                    let resource_ref = ResourceRef::from_context(&context);
+
+                    guard_exit_code(exit_code, stderr)?;
+                    let list = parse_json(&stdout)?;
+
+                    self.related_resources = related_resources(&list, &resource_ref);
+
+                    let mut lines = vec![format!(
+                        "Related to {}/{}",
+                        resource_ref.resource_type, resource_ref.name
+                    )];
+                    lines.extend(
+                        self.related_resources
+                            .iter()
+                            .map(|r| format!("  {}/{}", r.resource_type, r.name)),
+                    );
+
+                    self.resource_details = Some(lines);
+                    self.selected_resource_details_line = Some(1);
+                    self.resource_details_ref = Some(resource_ref);
+                    self.resource_details_kind = DetailsKind::Related;
+                    self.resource_details_offset = 0;
+
+                    Ok(())
+                }
+                "custom_action" => {
+                    let resource_ref = ResourceRef::from_context(&context);
+
+                    let mut lines = vec![format!(
+                        "# {}",
+                        context
+                            .get("action")
+                            .map(|a| a.as_str())
+                            .unwrap_or_default()
+                    )];
+                    lines.extend(output_lines(exit_code, stdout, stderr));
+
+                    self.resource_details = Some(lines);
+                    self.selected_resource_details_line = Some(0);
+                    self.resource_details_ref = Some(resource_ref);
+                    self.resource_details_kind = DetailsKind::Output;
+                    self.resource_details_offset = 0;
+
+                    Ok(())
+                }
+                "query_ports" => {
+                    let resource_ref = ResourceRef::from_context(&context);
+                    let result = self.result(exit_code, stdout, stderr, context, ' ')?;
+
+                    self.suggested_ports = Some((
+                        resource_ref,
+                        result.into_iter().filter(|p| !p.is_empty()).collect(),
+                    ));
+
+                    Ok(())
+                }
+                "diff_resource" => {
+                    let mut lines = vec!["# kubectl diff".to_owned()];
+                    let output = output_lines(exit_code, stdout, stderr);
+                    if output.is_empty() {
+                        lines.push("(no changes)".to_owned());
+                    }
+                    lines.extend(output);
+
+                    // the output no longer shows the resource, so it is not
+                    // edited or skipped by the preview
+                    self.resource_details = Some(lines);
+                    self.resource_details_ref = None;
+                    self.selected_resource_details_line = Some(0);
+                    self.resource_details_kind = DetailsKind::Output;
+
+                    Ok(())
+                }
+                "apply_resource" => {
+                    let mut lines = self.resource_details.take().unwrap_or_default();
+                    lines.push(format!(
+                        "# kubectl {}",
+                        context
+                            .get("strategy")
+                            .map(|s| s.as_str())
+                            .unwrap_or("apply")
+                    ));
+                    lines.extend(output_lines(exit_code, stdout, stderr));
+
+                    self.resource_details = Some(lines);
+
+                    Ok(())
+                }
                 _ => Ok(()),
             },
             None => Ok(()),
@@ -345,30 +1715,92 @@ impl State {
     }
 }
 
-fn get_next_item(items: &[String], selected_item: &Option<usize>, direction: ListDir) -> usize {
+fn get_next_item<T>(
+    items: &[T],
+    selected_item: &Option<usize>,
+    direction: ListDir,
+    wrap_around: bool,
+) -> usize {
     let selected_item = match selected_item {
         Some(rt) => *rt,
         None => 0,
     };
+    let last_item = items.len().saturating_sub(1);
 
     match direction {
         ListDir::Up => {
             if selected_item == 0 {
-                items.len().saturating_sub(1)
+                if wrap_around {
+                    last_item
+                } else {
+                    0
+                }
             } else {
                 selected_item.saturating_sub(1)
             }
         }
         ListDir::Down => {
-            if selected_item == items.len() - 1 {
-                0
+            if selected_item >= last_item {
+                if wrap_around {
+                    0
+                } else {
+                    last_item
+                }
             } else {
                 selected_item + 1
             }
         }
+        ListDir::PageUp(page_size) => selected_item.saturating_sub(page_size),
+        ListDir::PageDown(page_size) => cmp::min(selected_item + page_size, last_item),
+        ListDir::First => 0,
+        ListDir::Last => last_item,
+        ListDir::Line(line) => cmp::min(line, last_item),
     }
 }
 
+fn parse_json(stdout: &[u8]) -> Result<Value> {
+    serde_json::from_slice(stdout).map_err(|e| {
+        WrongExitCode {
+            src: NamedSource::new("kubernetes.rs", format!("Error parsing stdout: {}", e)),
+            bad_bit: (1, 2).into(),
+        }
+        .into()
+    })
+}
+
+fn sort_resources(resources: &mut [Resource], sort: &ResourceSort) {
+    // the name is the tie-breaker for all other fields
+    resources.sort_by(|a, b| {
+        let ordering = match sort.by {
+            SortBy::Name => a.name.cmp(&b.name),
+            // the youngest resources have the lowest age
+            SortBy::Age => b.created.cmp(&a.created),
+            SortBy::Status => a.status.cmp(&b.status),
+            SortBy::Restarts => a.restarts.cmp(&b.restarts),
+        }
+        .then_with(|| a.name.cmp(&b.name));
+
+        if sort.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    });
+}
+
+fn output_lines(exit_code: Option<i32>, stdout: Vec<u8>, stderr: Vec<u8>) -> Vec<String> {
+    let mut output = String::from_utf8_lossy(&stdout).to_string();
+    if exit_code != Some(0) {
+        output.push_str(&String::from_utf8_lossy(&stderr));
+    }
+
+    output
+        .lines()
+        .map(|l| l.to_string())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
 fn guard_exit_code(exit_code: Option<i32>, stderr: Vec<u8>) -> Result<()> {
     if let Some(code) = exit_code {
         if code != 0 {
@@ -1,2 +1,18 @@
+pub mod cache;
+pub mod command;
+pub mod config;
+pub mod custom;
+pub mod errors;
+pub mod favorites;
+pub mod history;
+pub mod keymap;
 pub mod kubernetes;
+pub mod loading;
+pub mod nodes;
+pub mod prompt;
+pub mod related;
 pub mod render;
+pub mod safety;
+pub mod scheduler;
+pub mod session;
+pub mod tree;
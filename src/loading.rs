@@ -0,0 +1,113 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+use crate::render::ColType;
+
+/// Seconds between refreshes of the metrics and nodes.
+pub const REFRESH_INTERVAL: f64 = 10.0;
+
+// appended to the header of columns, whose query is in flight
+const LOADING_MARKER: &str = "⟳";
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Timing {
+    // start of the query in flight
+    started: Option<DateTime<Utc>>,
+
+    // arrival and duration of the last result
+    loaded: Option<DateTime<Utc>>,
+    duration: Option<Duration>,
+}
+
+/// Queries in flight and the timing of the last results per column.
+#[derive(Debug, Default)]
+pub struct Loading {
+    columns: BTreeMap<ColType, Timing>,
+}
+
+/// Column, that shows the result of the command.
+fn column(command: &str) -> Option<ColType> {
+    match command {
+        "query_namespaces" => Some(ColType::Namespace),
+        "query_resource_types" => Some(ColType::ResourceType),
+        "query_resources" => Some(ColType::Resource),
+        "query_resource_details" | "query_owner_tree" | "query_related" | "custom_action" => {
+            Some(ColType::ResourceDetails)
+        }
+        _ => None,
+    }
+}
+
+impl Loading {
+    pub fn start(&mut self, command: &str) {
+        if let Some(col_type) = column(command) {
+            self.columns.entry(col_type).or_default().started = Some(Utc::now());
+        }
+    }
+
+    pub fn finish(&mut self, command: &str) {
+        let timing = match column(command).and_then(|c| self.columns.get_mut(&c)) {
+            Some(timing) => timing,
+            None => return,
+        };
+
+        let now = Utc::now();
+        timing.duration = timing.started.take().map(|started| now - started);
+        timing.loaded = Some(now);
+    }
+
+    /// Mark the data of the column as served from the cache, that was
+    /// queried at the given time.
+    pub fn cached(&mut self, command: &str, queried: DateTime<Utc>) {
+        if let Some(col_type) = column(command) {
+            let timing = self.columns.entry(col_type).or_default();
+            timing.loaded = Some(queried);
+            timing.duration = None;
+        }
+    }
+
+    pub fn fail(&mut self, command: &str) {
+        if let Some(timing) = column(command).and_then(|c| self.columns.get_mut(&c)) {
+            timing.started = None;
+        }
+    }
+
+    pub fn is_loading(&self, col_type: &ColType) -> bool {
+        self.columns
+            .get(col_type)
+            .is_some_and(|t| t.started.is_some())
+    }
+
+    /// Short status for the header of the column: a marker while its query
+    /// is in flight, the age of data older than `stale_after` or the
+    /// duration of the last query.
+    pub fn status(&self, col_type: &ColType, stale_after: Duration) -> Option<String> {
+        let timing = self.columns.get(col_type)?;
+
+        if timing.started.is_some() {
+            return Some(LOADING_MARKER.to_owned());
+        }
+
+        let age = Utc::now() - timing.loaded?;
+        if age > stale_after {
+            return Some(format!("stale {}", format_age(age)));
+        }
+
+        timing.duration.map(format_duration)
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    match duration.num_milliseconds() {
+        ms if ms >= 1000 => format!("{:.1}s", ms as f64 / 1000.0),
+        ms => format!("{}ms", ms),
+    }
+}
+
+fn format_age(age: Duration) -> String {
+    match age.num_seconds() {
+        s if s >= 3600 => format!("{}h", s / 3600),
+        s if s >= 60 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
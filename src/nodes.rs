@@ -0,0 +1,363 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::kubernetes::Metrics;
+
+/// Summary of a node for the nodes view.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NodeInfo {
+    pub name: String,
+    pub ready: bool,
+    pub unschedulable: bool,
+    pub roles: Vec<String>,
+    pub taints: Vec<String>,
+    pub version: String,
+
+    // millicores and bytes
+    pub cpu_allocatable: u64,
+    pub cpu_requested: u64,
+    pub memory_allocatable: u64,
+    pub memory_requested: u64,
+
+    pub pods: usize,
+    pub pods_allocatable: u64,
+}
+
+impl NodeInfo {
+    pub fn status(&self) -> String {
+        let ready = if self.ready { "Ready" } else { "NotReady" };
+
+        if self.unschedulable {
+            format!("{},SchedulingDisabled", ready)
+        } else {
+            ready.to_owned()
+        }
+    }
+}
+
+/// Summarize the nodes from the items of
+/// `kubectl get nodes,pods --all-namespaces --output json`. Requests and pod
+/// counts only include pods, that have not terminated.
+pub fn node_infos(list: &Value) -> Vec<NodeInfo> {
+    let items: Vec<&Value> = list["items"].as_array().into_iter().flatten().collect();
+
+    let pods: Vec<&Value> = items
+        .iter()
+        .filter(|i| i["kind"].as_str() == Some("Pod"))
+        .filter(|p| !matches!(p["status"]["phase"].as_str(), Some("Succeeded" | "Failed")))
+        .copied()
+        .collect();
+
+    items
+        .iter()
+        .filter(|i| i["kind"].as_str() == Some("Node"))
+        .map(|node| {
+            let name = node["metadata"]["name"].as_str().unwrap_or_default();
+            let allocatable = &node["status"]["allocatable"];
+
+            let node_pods: Vec<&&Value> = pods
+                .iter()
+                .filter(|p| p["spec"]["nodeName"].as_str() == Some(name))
+                .collect();
+
+            let requested = |resource: &str, parse: fn(&str) -> u64| -> u64 {
+                node_pods
+                    .iter()
+                    .flat_map(|p| p["spec"]["containers"].as_array().into_iter().flatten())
+                    .filter_map(|c| c["resources"]["requests"][resource].as_str())
+                    .map(parse)
+                    .sum()
+            };
+
+            NodeInfo {
+                name: name.to_owned(),
+                ready: node["status"]["conditions"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .any(|c| c["type"] == "Ready" && c["status"] == "True"),
+                unschedulable: node["spec"]["unschedulable"].as_bool().unwrap_or(false),
+                roles: node["metadata"]["labels"]
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(label, _)| label.strip_prefix("node-role.kubernetes.io/"))
+                    .map(|role| role.to_owned())
+                    .collect(),
+                taints: node["spec"]["taints"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|t| {
+                        let key = t["key"].as_str().unwrap_or_default();
+                        let effect = t["effect"].as_str().unwrap_or_default();
+
+                        match t["value"].as_str() {
+                            Some(value) => format!("{}={}:{}", key, value, effect),
+                            None => format!("{}:{}", key, effect),
+                        }
+                    })
+                    .collect(),
+                version: node["status"]["nodeInfo"]["kubeletVersion"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_owned(),
+                cpu_allocatable: allocatable["cpu"].as_str().map(parse_cpu).unwrap_or(0),
+                cpu_requested: requested("cpu", parse_cpu),
+                memory_allocatable: allocatable["memory"]
+                    .as_str()
+                    .map(parse_memory)
+                    .unwrap_or(0),
+                memory_requested: requested("memory", parse_memory),
+                pods: node_pods.len(),
+                pods_allocatable: allocatable["pods"]
+                    .as_str()
+                    .and_then(|p| p.parse().ok())
+                    .unwrap_or(0),
+            }
+        })
+        .collect()
+}
+
+/// Header and aligned rows of the nodes table. The usage columns from
+/// `kubectl top node` are only shown, when metrics are available.
+pub fn node_table(
+    nodes: &[NodeInfo],
+    metrics: &BTreeMap<String, Metrics>,
+) -> (String, Vec<String>) {
+    let mut header = vec![
+        "NAME", "STATUS", "ROLES", "VERSION", "CPU", "MEMORY", "PODS", "TAINTS",
+    ];
+    if !metrics.is_empty() {
+        header.splice(4..4, ["CPU USAGE", "MEMORY USAGE"]);
+    }
+    let header: Vec<String> = header.into_iter().map(|h| h.to_owned()).collect();
+
+    let rows: Vec<Vec<String>> = nodes
+        .iter()
+        .map(|n| {
+            let mut row = vec![
+                n.name.clone(),
+                n.status(),
+                if n.roles.is_empty() {
+                    "<none>".to_owned()
+                } else {
+                    n.roles.join(",")
+                },
+                n.version.clone(),
+                format!(
+                    "{}/{} ({})",
+                    format_cpu(n.cpu_requested),
+                    format_cpu(n.cpu_allocatable),
+                    percent(n.cpu_requested, n.cpu_allocatable)
+                ),
+                format!(
+                    "{}/{} ({})",
+                    format_memory(n.memory_requested),
+                    format_memory(n.memory_allocatable),
+                    percent(n.memory_requested, n.memory_allocatable)
+                ),
+                format!("{}/{}", n.pods, n.pods_allocatable),
+                n.taints.join(" "),
+            ];
+
+            if !metrics.is_empty() {
+                let usage = metrics.get(&n.name);
+                row.splice(
+                    4..4,
+                    [
+                        usage.map_or("-".to_owned(), |m| m.cpu.clone()),
+                        usage.map_or("-".to_owned(), |m| m.memory.clone()),
+                    ],
+                );
+            }
+
+            row
+        })
+        .collect();
+
+    let widths: Vec<usize> = (0..header.len())
+        .map(|i| {
+            rows.iter()
+                .map(|r| console::measure_text_width(&r[i]))
+                .chain([header[i].len()])
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let align = |columns: &[String]| -> String {
+        columns
+            .iter()
+            .zip(&widths)
+            .map(|(c, w)| console::pad_str(c, *w, console::Alignment::Left, None).to_string())
+            .collect::<Vec<String>>()
+            .join("  ")
+            .trim_end()
+            .to_owned()
+    };
+
+    (align(&header), rows.iter().map(|r| align(r)).collect())
+}
+
+/// Parse a CPU quantity, e.g. `250m` or `2`, into millicores.
+fn parse_cpu(quantity: &str) -> u64 {
+    match quantity.strip_suffix('m') {
+        Some(millis) => millis.parse().unwrap_or(0),
+        None => quantity
+            .parse::<f64>()
+            .map(|cores| (cores * 1000.0) as u64)
+            .unwrap_or(0),
+    }
+}
+
+/// Parse a memory quantity, e.g. `128Mi`, `1G`, `1048576` or `1500m`, into
+/// bytes. The API server emits millibytes for quantities with fractions.
+fn parse_memory(quantity: &str) -> u64 {
+    if let Some(millis) = quantity.strip_suffix('m') {
+        return millis
+            .parse::<f64>()
+            .map(|v| (v / 1000.0) as u64)
+            .unwrap_or(0);
+    }
+
+    const SUFFIXES: [(&str, u64); 10] = [
+        ("Ki", 1 << 10),
+        ("Mi", 1 << 20),
+        ("Gi", 1 << 30),
+        ("Ti", 1 << 40),
+        ("Pi", 1 << 50),
+        ("k", 1_000),
+        ("M", 1_000_000),
+        ("G", 1_000_000_000),
+        ("T", 1_000_000_000_000),
+        ("P", 1_000_000_000_000_000),
+    ];
+
+    for (suffix, factor) in SUFFIXES {
+        if let Some(value) = quantity.strip_suffix(suffix) {
+            return value
+                .parse::<f64>()
+                .map(|v| (v * factor as f64) as u64)
+                .unwrap_or(0);
+        }
+    }
+
+    quantity.parse::<f64>().map(|v| v as u64).unwrap_or(0)
+}
+
+fn format_cpu(millis: u64) -> String {
+    if millis % 1000 == 0 {
+        format!("{}", millis / 1000)
+    } else {
+        format!("{}m", millis)
+    }
+}
+
+fn format_memory(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1 << 30 => format!("{:.1}Gi", b as f64 / (1u64 << 30) as f64),
+        b => format!("{}Mi", b >> 20),
+    }
+}
+
+fn percent(part: u64, total: u64) -> String {
+    if total == 0 {
+        return "-".to_owned();
+    }
+
+    format!("{}%", part * 100 / total)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn cpu_quantities() {
+        assert_eq!(parse_cpu("250m"), 250);
+        assert_eq!(parse_cpu("2"), 2000);
+        assert_eq!(parse_cpu("0.5"), 500);
+        assert_eq!(parse_cpu("invalid"), 0);
+    }
+
+    #[test]
+    fn memory_quantities() {
+        assert_eq!(parse_memory("128Mi"), 128 << 20);
+        assert_eq!(parse_memory("1Gi"), 1 << 30);
+        assert_eq!(parse_memory("1G"), 1_000_000_000);
+        assert_eq!(parse_memory("500k"), 500_000);
+        assert_eq!(parse_memory("1048576"), 1_048_576);
+        assert_eq!(parse_memory("1500m"), 1);
+        assert_eq!(parse_memory("134217728000m"), 134_217_728);
+        assert_eq!(parse_memory("invalid"), 0);
+    }
+
+    #[test]
+    fn node_infos_sum_up_the_pods_of_each_node() {
+        let pod = |name: &str, node: &str, phase: &str, cpu: &str, memory: &str| {
+            json!({
+                "kind": "Pod",
+                "metadata": { "name": name },
+                "spec": {
+                    "nodeName": node,
+                    "containers": [{ "resources": { "requests": { "cpu": cpu, "memory": memory } } }],
+                },
+                "status": { "phase": phase },
+            })
+        };
+
+        let list = json!({
+            "items": [
+                {
+                    "kind": "Node",
+                    "metadata": {
+                        "name": "node-1",
+                        "labels": { "node-role.kubernetes.io/control-plane": "" },
+                    },
+                    "spec": {
+                        "unschedulable": true,
+                        "taints": [{ "key": "dedicated", "value": "db", "effect": "NoSchedule" }],
+                    },
+                    "status": {
+                        "conditions": [{ "type": "Ready", "status": "True" }],
+                        "allocatable": { "cpu": "4", "memory": "8Gi", "pods": "110" },
+                        "nodeInfo": { "kubeletVersion": "v1.30.0" },
+                    },
+                },
+                { "kind": "Node", "metadata": { "name": "node-2" } },
+                pod("a", "node-1", "Running", "250m", "128Mi"),
+                pod("b", "node-1", "Pending", "500m", "256Mi"),
+                pod("c", "node-1", "Succeeded", "1", "1Gi"),
+                pod("d", "node-2", "Running", "100m", "64Mi"),
+            ],
+        });
+
+        let nodes = node_infos(&list);
+
+        assert_eq!(
+            nodes[0],
+            NodeInfo {
+                name: "node-1".to_owned(),
+                ready: true,
+                unschedulable: true,
+                roles: vec!["control-plane".to_owned()],
+                taints: vec!["dedicated=db:NoSchedule".to_owned()],
+                version: "v1.30.0".to_owned(),
+                cpu_allocatable: 4000,
+                cpu_requested: 750,
+                memory_allocatable: 8 << 30,
+                memory_requested: 384 << 20,
+                pods: 2,
+                pods_allocatable: 110,
+            }
+        );
+        assert_eq!(nodes[0].status(), "Ready,SchedulingDisabled");
+
+        assert_eq!(nodes[1].name, "node-2");
+        assert!(!nodes[1].ready);
+        assert_eq!(nodes[1].pods, 1);
+        assert_eq!(nodes[1].cpu_requested, 100);
+    }
+}
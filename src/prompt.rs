@@ -0,0 +1,62 @@
+use zellij_tile::prelude::*;
+
+use crate::kubernetes::ResourceRef;
+use crate::safety::MutatingAction;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PromptAction {
+    PortForward(ResourceRef),
+    Confirm(MutatingAction),
+    ConfirmYes(MutatingAction),
+    DrainOptions(String),
+    JumpToLine,
+    Command,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PromptResult {
+    Pending,
+    Cancel,
+    Complete,
+    Submit(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct Prompt {
+    pub title: String,
+    pub input: String,
+    pub action: PromptAction,
+
+    // shown after the input, e.g. the candidates of a completion
+    pub hint: String,
+}
+
+impl Prompt {
+    pub fn new(title: &str, input: &str, action: PromptAction) -> Self {
+        Self {
+            title: title.to_owned(),
+            input: input.to_owned(),
+            action,
+            hint: String::new(),
+        }
+    }
+
+    pub fn handle_key(&mut self, key: &KeyWithModifier) -> PromptResult {
+        match key.bare_key {
+            BareKey::Enter => PromptResult::Submit(self.input.trim().to_owned()),
+            BareKey::Esc => PromptResult::Cancel,
+            BareKey::Tab => PromptResult::Complete,
+            BareKey::Backspace => {
+                self.input.pop();
+
+                PromptResult::Pending
+            }
+            BareKey::Char(c) => {
+                self.input.push(c);
+
+                PromptResult::Pending
+            }
+            _ => PromptResult::Pending,
+        }
+    }
+}
@@ -0,0 +1,134 @@
+use itertools::Itertools;
+use serde_json::Value;
+
+use crate::kubernetes::ResourceRef;
+use crate::tree::selects;
+
+/// Resource kinds, for which related resources can be listed.
+pub fn supports_related(resource_type: &str) -> bool {
+    matches!(resource_type, "Pod" | "Service" | "Ingress")
+}
+
+/// Collect the resources related to the given one from the items of
+/// `kubectl get pods,services,ingresses,endpoints --output json`.
+///
+/// - Pods relate to their node, owners, service account and the mounted
+///   ConfigMaps, Secrets and PVCs
+/// - Services relate to their endpoints and the pods they select
+/// - Ingresses relate to their backend services
+pub fn related_resources(list: &Value, resource_ref: &ResourceRef) -> Vec<ResourceRef> {
+    let items: Vec<&Value> = list["items"].as_array().into_iter().flatten().collect();
+
+    let item = match items.iter().find(|i| {
+        i["kind"].as_str() == Some(&resource_ref.resource_type)
+            && i["metadata"]["name"].as_str() == Some(&resource_ref.name)
+    }) {
+        Some(item) => *item,
+        None => return vec![],
+    };
+
+    let related: Vec<(String, String)> = match resource_ref.resource_type.as_str() {
+        "Pod" => pod_related(item),
+        "Service" => {
+            let mut related = vec![("Endpoints".to_owned(), resource_ref.name.clone())];
+            related.extend(
+                items
+                    .iter()
+                    .filter(|i| i["kind"].as_str() == Some("Pod") && selects(item, i))
+                    .filter_map(|p| p["metadata"]["name"].as_str())
+                    .map(|name| ("Pod".to_owned(), name.to_owned())),
+            );
+
+            related
+        }
+        "Ingress" => ingress_related(item),
+        _ => vec![],
+    };
+
+    related
+        .into_iter()
+        .unique()
+        .map(|(kind, name)| ResourceRef {
+            namespace: resource_ref.namespace.clone(),
+            resource_type: kind,
+            name,
+        })
+        .collect()
+}
+
+fn pod_related(pod: &Value) -> Vec<(String, String)> {
+    let mut related: Vec<(String, String)> = vec![];
+    let name = |value: &Value| value.as_str().map(|s| s.to_owned());
+
+    if let Some(node) = name(&pod["spec"]["nodeName"]) {
+        related.push(("Node".to_owned(), node));
+    }
+
+    for owner in pod["metadata"]["ownerReferences"]
+        .as_array()
+        .into_iter()
+        .flatten()
+    {
+        if let (Some(kind), Some(owner_name)) = (owner["kind"].as_str(), name(&owner["name"])) {
+            related.push((kind.to_owned(), owner_name));
+        }
+    }
+
+    if let Some(service_account) = name(&pod["spec"]["serviceAccountName"]) {
+        related.push(("ServiceAccount".to_owned(), service_account));
+    }
+
+    for volume in pod["spec"]["volumes"].as_array().into_iter().flatten() {
+        if let Some(config_map) = name(&volume["configMap"]["name"]) {
+            related.push(("ConfigMap".to_owned(), config_map));
+        }
+        if let Some(secret) = name(&volume["secret"]["secretName"]) {
+            related.push(("Secret".to_owned(), secret));
+        }
+        if let Some(claim) = name(&volume["persistentVolumeClaim"]["claimName"]) {
+            related.push(("PersistentVolumeClaim".to_owned(), claim));
+        }
+    }
+
+    for container in pod["spec"]["containers"].as_array().into_iter().flatten() {
+        for env_from in container["envFrom"].as_array().into_iter().flatten() {
+            if let Some(config_map) = name(&env_from["configMapRef"]["name"]) {
+                related.push(("ConfigMap".to_owned(), config_map));
+            }
+            if let Some(secret) = name(&env_from["secretRef"]["name"]) {
+                related.push(("Secret".to_owned(), secret));
+            }
+        }
+
+        for env in container["env"].as_array().into_iter().flatten() {
+            if let Some(config_map) = name(&env["valueFrom"]["configMapKeyRef"]["name"]) {
+                related.push(("ConfigMap".to_owned(), config_map));
+            }
+            if let Some(secret) = name(&env["valueFrom"]["secretKeyRef"]["name"]) {
+                related.push(("Secret".to_owned(), secret));
+            }
+        }
+    }
+
+    related
+}
+
+fn ingress_related(ingress: &Value) -> Vec<(String, String)> {
+    let mut backends: Vec<&Value> = vec![&ingress["spec"]["defaultBackend"]];
+
+    for rule in ingress["spec"]["rules"].as_array().into_iter().flatten() {
+        backends.extend(
+            rule["http"]["paths"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .map(|p| &p["backend"]),
+        );
+    }
+
+    backends
+        .into_iter()
+        .filter_map(|b| b["service"]["name"].as_str())
+        .map(|name| ("Service".to_owned(), name.to_owned()))
+        .collect()
+}
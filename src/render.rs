@@ -1,10 +1,21 @@
 use miette::{Diagnostic, NamedSource, Report, Result, SourceSpan};
-use std::cmp;
+use serde::{Deserialize, Serialize};
+use std::{cmp, collections::BTreeMap};
 use thiserror::Error;
 
 use anstyle::{Ansi256Color, AnsiColor, Color, RgbColor, Style};
 
-use super::kubernetes::State;
+use super::errors::{Errors, PluginError};
+use super::kubernetes::{Metrics, Resource, State};
+use super::nodes::node_table;
+use super::prompt::Prompt;
+use super::safety::glob_match;
+
+// prefix of starred namespaces and resources
+const FAVORITE_MARKER: &str = "★";
+
+// shown in columns, whose first query is in flight
+const LOADING_PLACEHOLDER: &str = "loading…";
 
 #[derive(Error, Debug, Diagnostic)]
 #[error("Invalid Color")]
@@ -28,7 +39,8 @@ struct MissingConfigValue {
     bad_bit: SourceSpan,
 }
 
-#[derive(Eq, Ord, PartialEq, PartialOrd, Debug, Copy, Clone)]
+#[derive(Eq, Ord, PartialEq, PartialOrd, Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
 pub enum ColType {
     Namespace,
     ResourceType,
@@ -36,17 +48,91 @@ pub enum ColType {
     ResourceDetails,
 }
 
+impl ColType {
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::Namespace => "Namespaces",
+            Self::ResourceType => "Resource Types",
+            Self::Resource => "Resources",
+            Self::ResourceDetails => "Resource Details",
+        }
+    }
+}
+
 impl Default for ColType {
     fn default() -> Self {
         Self::Namespace
     }
 }
 
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum View {
+    Resources,
+    PortForwards,
+    Favorites,
+    Nodes,
+}
+
+impl Default for View {
+    fn default() -> Self {
+        Self::Resources
+    }
+}
+
 #[derive(Debug)]
 struct Col {
+    pub col_type: Option<ColType>,
     pub max_width: usize,
     pub items: Vec<Item>,
     pub selected: bool,
+    pub scroll: usize,
+}
+
+/// Position of a rendered column, such that mouse events can be mapped
+/// back to the column and its items.
+#[derive(Debug, Clone)]
+struct ColLayout {
+    col_type: Option<ColType>,
+    x_start: usize,
+    x_end: usize,
+    scroll: usize,
+    item_count: usize,
+}
+
+/// Width budget of a column in the layout. Columns get at least `min` and
+/// at most `max` characters, the remaining space is shared by `weight`.
+struct ColBudget {
+    min: usize,
+    max: usize,
+    weight: usize,
+}
+
+impl ColBudget {
+    fn of(col_type: Option<ColType>) -> Self {
+        match col_type {
+            Some(ColType::Namespace) => Self {
+                min: 10,
+                max: 30,
+                weight: 1,
+            },
+            Some(ColType::ResourceType) => Self {
+                min: 10,
+                max: 25,
+                weight: 1,
+            },
+            Some(ColType::Resource) => Self {
+                min: 12,
+                max: 50,
+                weight: 2,
+            },
+            Some(ColType::ResourceDetails) | None => Self {
+                min: 20,
+                max: usize::MAX,
+                weight: 4,
+            },
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -61,6 +147,16 @@ pub struct Render {
     selected_style: Style,
     selected_col_style: Style,
     selected_col_selected_style: Style,
+    protected_style: Style,
+    context_styles: Vec<(String, Style)>,
+
+    // layout of the last rendered table
+    layout: Vec<ColLayout>,
+    table_top: usize,
+
+    // first wrapped line of every line of the resource details, empty when
+    // the details are not wrapped
+    details_line_map: Vec<usize>,
 }
 
 impl Render {
@@ -68,6 +164,7 @@ impl Render {
         selected_bg: Option<&str>,
         selected_col_bg: Option<&str>,
         selected_col_selected_bg: Option<&str>,
+        context_colors: Option<&str>,
     ) -> Result<Self> {
         let normal_style = Style::new();
 
@@ -126,11 +223,21 @@ impl Render {
             selected_col_selected_style.bg_color(Some(selected_col_selected_bg));
         selected_col_selected_style = selected_col_selected_style.bold();
 
+        let protected_style = Style::new()
+            .bg_color(Some(AnsiColor::Red.into()))
+            .fg_color(Some(AnsiColor::White.into()))
+            .bold();
+
+        let context_styles = parse_context_colors(context_colors.unwrap_or_default())?;
+
         Ok(Self {
             normal_style,
             selected_style,
             selected_col_style,
             selected_col_selected_style,
+            protected_style,
+            context_styles,
+            ..Default::default()
         })
     }
 
@@ -138,18 +245,52 @@ impl Render {
         &mut self,
         state: &State,
         selected_col: &ColType,
+        protected: bool,
+        help: Option<&[String]>,
         rows: usize,
         cols: usize,
     ) {
+        let mut rows = rows;
+        self.table_top = 0;
+        self.layout = vec![];
+
+        if state.context_info.is_some() {
+            self.render_header(state, protected, cols);
+            rows = rows.saturating_sub(1);
+            self.table_top = 1;
+        }
+
         let mut output: Vec<Col> = vec![];
 
+        let placeholder = vec![LOADING_PLACEHOLDER.to_owned()];
+
         if let Some(namespaces) = &state.namespaces {
+            let labels: Vec<String> = namespaces
+                .iter()
+                .map(|n| {
+                    if state.is_favorite_namespace(n) {
+                        format!("{} {}", FAVORITE_MARKER, n)
+                    } else {
+                        n.to_owned()
+                    }
+                })
+                .collect();
+
             output.push(self.get_col(
-                namespaces,
-                ColType::Namespace,
+                &labels,
+                Some(ColType::Namespace),
                 &state.selected_namespace,
-                selected_col,
-                "Namespaces",
+                *selected_col == ColType::Namespace,
+                &column_header(state, ColType::Namespace, ColType::Namespace.header()),
+                rows,
+            ));
+        } else if state.loading.is_loading(&ColType::Namespace) {
+            output.push(self.get_col(
+                &placeholder,
+                Some(ColType::Namespace),
+                &None,
+                *selected_col == ColType::Namespace,
+                &column_header(state, ColType::Namespace, ColType::Namespace.header()),
                 rows,
             ));
         }
@@ -157,32 +298,115 @@ impl Render {
         if let Some(resource_types) = &state.resource_types {
             output.push(self.get_col(
                 resource_types,
-                ColType::ResourceType,
+                Some(ColType::ResourceType),
                 &state.selected_resource_type,
-                selected_col,
-                "Resource Types",
+                *selected_col == ColType::ResourceType,
+                &column_header(state, ColType::ResourceType, ColType::ResourceType.header()),
+                rows,
+            ));
+        } else if state.loading.is_loading(&ColType::ResourceType) {
+            output.push(self.get_col(
+                &placeholder,
+                Some(ColType::ResourceType),
+                &None,
+                *selected_col == ColType::ResourceType,
+                &column_header(state, ColType::ResourceType, ColType::ResourceType.header()),
                 rows,
             ));
         }
 
+        let resource_header = format!("{} [{}]", ColType::Resource.header(), state.resource_sort);
+
         if let Some(resources) = &state.resources {
+            let favorites = state.resource_favorites();
+            let mut labels: Vec<String> = resources
+                .iter()
+                .map(|r| {
+                    if favorites.contains(&r.name) {
+                        format!("{} {}", FAVORITE_MARKER, r.label(&state.resource_sort))
+                    } else {
+                        r.label(&state.resource_sort)
+                    }
+                })
+                .collect();
+
+            if !state.metrics.is_empty() {
+                labels = metrics_labels(&labels, resources, &state.metrics);
+            }
+
             output.push(self.get_col(
-                resources,
-                ColType::Resource,
+                &labels,
+                Some(ColType::Resource),
                 &state.selected_resource,
-                selected_col,
-                "Resources",
+                *selected_col == ColType::Resource,
+                &column_header(state, ColType::Resource, &resource_header),
+                rows,
+            ));
+        } else if state.loading.is_loading(&ColType::Resource) {
+            output.push(self.get_col(
+                &placeholder,
+                Some(ColType::Resource),
+                &None,
+                *selected_col == ColType::Resource,
+                &column_header(state, ColType::Resource, &resource_header),
                 rows,
             ));
         }
 
+        self.details_line_map = vec![];
+        let details_header = column_header(
+            state,
+            ColType::ResourceDetails,
+            ColType::ResourceDetails.header(),
+        );
+
         if let Some(resource_details) = &state.resource_details {
-            output.push(self.get_col(
-                resource_details,
-                ColType::ResourceDetails,
+            let resource_details: Vec<String> = resource_details
+                .iter()
+                .map(|l| l.chars().skip(state.resource_details_offset).collect())
+                .collect();
+
+            let mut col = self.get_col(
+                &resource_details,
+                Some(ColType::ResourceDetails),
                 &state.selected_resource_details_line,
-                selected_col,
-                "Resource Details",
+                *selected_col == ColType::ResourceDetails,
+                &details_header,
+                rows,
+            );
+
+            // wrap the details at the width they would get in the layout and
+            // map the selected line to its first wrapped line
+            if state.wrap_resource_details {
+                output.push(col);
+                let (_, widths) = allocate_widths(&output, cols);
+                output.pop();
+
+                let width = widths.last().copied().unwrap_or(cols);
+                let (wrapped, line_map) = wrap_lines(&resource_details, width);
+                let selected_line = state
+                    .selected_resource_details_line
+                    .and_then(|l| line_map.get(l).copied());
+
+                col = self.get_col(
+                    &wrapped,
+                    Some(ColType::ResourceDetails),
+                    &selected_line,
+                    *selected_col == ColType::ResourceDetails,
+                    &details_header,
+                    rows,
+                );
+                self.details_line_map = line_map;
+            }
+
+            output.push(col);
+        } else if state.loading.is_loading(&ColType::ResourceDetails) {
+            output.push(self.get_col(
+                &placeholder,
+                Some(ColType::ResourceDetails),
+                &None,
+                *selected_col == ColType::ResourceDetails,
+                &details_header,
                 rows,
             ));
         }
@@ -191,15 +415,217 @@ impl Render {
             return;
         }
 
-        self.render_table(output, cols);
+        self.render_table(output, help, rows, cols);
+    }
+
+    /// Render the status line with the context, server, user and namespace,
+    /// that the plugin is pointed at, colored by the context.
+    fn render_header(&self, state: &State, protected: bool, cols: usize) {
+        let context_info = match &state.context_info {
+            Some(context_info) => context_info,
+            None => return,
+        };
+
+        let mut text = format!(
+            "context: {}  server: {}  user: {}",
+            context_info.name, context_info.server, context_info.user
+        );
+        if let Some(namespace) = state.get_selected_item(&ColType::Namespace) {
+            text = format!("{}  namespace: {}", text, namespace);
+        }
+
+        if protected {
+            self.render_line(&format!("PROTECTED  {}", text), &self.protected_style, cols);
+            return;
+        }
+
+        let style = self
+            .context_styles
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, &context_info.name))
+            .map(|(_, style)| style)
+            .unwrap_or(&self.selected_col_style);
+
+        self.render_line(&text, style, cols);
+    }
+
+    pub fn render_port_forwards(
+        &mut self,
+        state: &State,
+        help: Option<&[String]>,
+        rows: usize,
+        cols: usize,
+    ) {
+        let mut port_forwards: Vec<String> = state
+            .port_forwards
+            .iter()
+            .map(|p| {
+                let status = match (p.exited, p.exit_code) {
+                    (false, _) => "running".to_owned(),
+                    (true, Some(code)) => format!("exited with {}", code),
+                    (true, None) => "exited".to_owned(),
+                };
+
+                format!(
+                    "{}/{}/{}  {}  [{}]",
+                    p.resource_ref.namespace,
+                    p.resource_ref.resource_type.to_lowercase(),
+                    p.resource_ref.name,
+                    p.ports,
+                    status,
+                )
+            })
+            .collect();
+
+        if port_forwards.is_empty() {
+            port_forwards.push("No active port forwards".to_owned());
+        }
+
+        self.table_top = 0;
+
+        let col = self.get_col(
+            &port_forwards,
+            None,
+            &state.selected_port_forward,
+            true,
+            "Port Forwards",
+            rows,
+        );
+
+        self.render_table(vec![col], help, rows, cols);
+    }
+
+    pub fn render_favorites(
+        &mut self,
+        state: &State,
+        help: Option<&[String]>,
+        rows: usize,
+        cols: usize,
+    ) {
+        let mut favorites: Vec<String> = state
+            .favorites
+            .items()
+            .iter()
+            .map(|f| format!("{} {}", FAVORITE_MARKER, f.label()))
+            .collect();
+
+        if favorites.is_empty() {
+            favorites.push("No favorites".to_owned());
+        }
+
+        self.table_top = 0;
+
+        let col = self.get_col(
+            &favorites,
+            None,
+            &state.selected_favorite,
+            true,
+            "Favorites",
+            rows,
+        );
+
+        self.render_table(vec![col], help, rows, cols);
+    }
+
+    pub fn render_nodes(
+        &mut self,
+        state: &State,
+        help: Option<&[String]>,
+        rows: usize,
+        cols: usize,
+    ) {
+        let (header, mut nodes) = match &state.nodes {
+            Some(nodes) => node_table(nodes, &state.node_metrics),
+            None => ("Nodes".to_owned(), vec!["Loading nodes...".to_owned()]),
+        };
+
+        if nodes.is_empty() {
+            nodes.push("No nodes".to_owned());
+        }
+
+        self.table_top = 0;
+
+        let col = self.get_col(&nodes, None, &state.selected_node, true, &header, rows);
+
+        self.render_table(vec![col], help, rows, cols);
+    }
+
+    pub fn render_prompt(&self, prompt: &Prompt, cols: usize) {
+        let hint = if prompt.hint.is_empty() {
+            String::new()
+        } else {
+            format!("   {}", prompt.hint)
+        };
+
+        self.render_line(
+            &format!("{}: {}_{}", prompt.title, prompt.input, hint),
+            &self.selected_col_style,
+            cols,
+        );
+    }
+
+    pub fn render_notice(&self, notice: &str, cols: usize) {
+        self.render_line(notice, &self.selected_col_style, cols);
+    }
+
+    /// Render the latest error below the columns. The first line names the
+    /// failed command and the keys to retry or dismiss it, followed by the
+    /// diagnostic.
+    pub fn render_errors(&self, errors: &Errors, hint: &str, rows: usize, cols: usize) {
+        let error = match errors.latest() {
+            Some(error) => error,
+            None => return,
+        };
+
+        let title = match error.command() {
+            Some(command) => format!("Error in {}", command),
+            None => "Error".to_owned(),
+        };
+        let count = if errors.len() > 1 {
+            format!(" (1 of {})", errors.len())
+        } else {
+            String::new()
+        };
+
+        self.render_line(
+            &format!("{}{}  {}", title, count, hint),
+            &self.protected_style,
+            cols,
+        );
+
+        for line in error_lines(error).iter().take(rows.saturating_sub(1)) {
+            println!(
+                "{}",
+                console::pad_str(
+                    &format!(" {}", line),
+                    cols,
+                    console::Alignment::Left,
+                    Some("")
+                )
+            );
+        }
+    }
+
+    fn render_line(&self, text: &str, style: &Style, cols: usize) {
+        println!(
+            "{}{}{}",
+            style.render(),
+            console::pad_str(
+                &format!(" {}", text),
+                cols,
+                console::Alignment::Left,
+                Some("")
+            ),
+            style.render_reset(),
+        );
     }
 
     fn get_col(
         &mut self,
         data: &[String],
-        col_type: ColType,
+        col_type: Option<ColType>,
         selected_data_index: &Option<usize>,
-        selected_col: &ColType,
+        selected: bool,
         header: &str,
         rows: usize,
     ) -> Col {
@@ -216,15 +642,20 @@ impl Render {
             })
             .collect();
 
-        items[*srt].selected = true;
+        if let Some(item) = items.get_mut(*srt) {
+            item.selected = true;
+        }
 
+        let mut scroll = 0;
+        // small terminals leave no rows for the items
+        let visible = rows.saturating_sub(2);
         if items.len() > rows {
-            let scroll = cmp::min(
+            scroll = cmp::min(
                 srt.saturating_sub(rows / 2),
-                items.len().saturating_sub(rows - 2),
+                items.len().saturating_sub(visible),
             );
 
-            items = items[scroll..scroll + rows - 2].to_vec();
+            items = items[scroll..scroll + visible].to_vec();
         }
 
         items.insert(
@@ -236,17 +667,26 @@ impl Render {
         );
 
         Col {
+            col_type,
+            scroll,
             max_width: items
                 .iter()
                 .map(|i| console::measure_text_width(&i.name))
                 .max()
                 .unwrap(),
             items,
-            selected: *selected_col == col_type,
+            selected,
         }
     }
 
-    fn render_table(&mut self, table: Vec<Col>, cols: usize) {
+    fn render_table(
+        &mut self,
+        table: Vec<Col>,
+        overlay: Option<&[String]>,
+        rows: usize,
+        cols: usize,
+    ) {
+        let table = fit_cols(table, cols);
         let mut output_rows: Vec<String> = vec![];
 
         let max_row_count = table.iter().map(|c| c.items.len()).max().unwrap();
@@ -255,12 +695,27 @@ impl Render {
             output_rows.push("".to_owned());
         }
 
+        self.layout = vec![];
+
         let mut col_counter = 0;
         for col in &table {
             let mut counter = 0;
             col_counter += 1;
             let last_col = col_counter == table.len();
 
+            let x_start = console::measure_text_width(&output_rows[0]);
+            self.layout.push(ColLayout {
+                col_type: col.col_type,
+                x_start,
+                x_end: if last_col {
+                    cols
+                } else {
+                    x_start + col.max_width + 2
+                },
+                scroll: col.scroll,
+                item_count: col.items.len().saturating_sub(1),
+            });
+
             for item in &col.items {
                 counter += 1;
                 let mut space_count = col.max_width - console::measure_text_width(&item.name);
@@ -296,6 +751,10 @@ impl Render {
             }
         }
 
+        if let Some(overlay) = overlay {
+            self.render_overlay(&mut output_rows, overlay, rows, cols);
+        }
+
         for output_row in output_rows {
             let mut print_text = output_row.clone();
             if console::measure_text_width(&output_row) > cols {
@@ -305,6 +764,260 @@ impl Render {
             println!("{}{}", print_text, self.selected_col_style.render_reset());
         }
     }
+
+    /// Map a position in the pane to the column and the index of the item
+    /// at this position, based on the layout of the last rendered table.
+    pub fn hit_test(&self, line: usize, column: usize) -> Option<(ColType, Option<usize>)> {
+        let col = self
+            .layout
+            .iter()
+            .find(|c| c.x_start <= column && column < c.x_end)?;
+        let col_type = col.col_type?;
+
+        // the first line of the table contains the column headers
+        let mut item = match line.checked_sub(self.table_top + 1) {
+            Some(row) if row < col.item_count => Some(col.scroll + row),
+            _ => None,
+        };
+
+        if col_type == ColType::ResourceDetails && !self.details_line_map.is_empty() {
+            item = item.map(|i| {
+                self.details_line_map
+                    .partition_point(|first| *first <= i)
+                    .saturating_sub(1)
+            });
+        }
+
+        Some((col_type, item))
+    }
+
+    /// Draw the overlay lines as a panel on the right side over the rows of
+    /// the table, below the column headers.
+    fn render_overlay(
+        &self,
+        output_rows: &mut Vec<String>,
+        overlay: &[String],
+        rows: usize,
+        cols: usize,
+    ) {
+        let width = overlay
+            .iter()
+            .map(|l| console::measure_text_width(l))
+            .max()
+            .unwrap_or(0)
+            + 2;
+        let left = cols.saturating_sub(width);
+
+        while output_rows.len() <= overlay.len() && output_rows.len() < rows {
+            output_rows.push("".to_owned());
+        }
+
+        for (index, line) in overlay.iter().enumerate() {
+            let output_row = match output_rows.get_mut(index + 1) {
+                Some(output_row) => output_row,
+                None => break,
+            };
+
+            *output_row = format!(
+                "{}{}{} {} {}",
+                console::pad_str(output_row, left, console::Alignment::Left, Some("")),
+                self.selected_col_style.render_reset(),
+                self.selected_col_style.render(),
+                console::pad_str(line, width - 2, console::Alignment::Left, Some("")),
+                self.selected_col_style.render_reset(),
+            );
+        }
+    }
+}
+
+/// Allocate the available width to the columns. When the minimal widths do
+/// not fit, the columns left of the focused one are collapsed. Long names are
+/// shortened in the middle, details are cut off, as they scroll horizontally.
+fn fit_cols(mut table: Vec<Col>, cols: usize) -> Vec<Col> {
+    let (first, widths) = allocate_widths(&table, cols);
+
+    table.drain(..first);
+
+    for (col, width) in table.iter_mut().zip(widths) {
+        for item in col.items.iter_mut() {
+            item.name = match col.col_type {
+                Some(ColType::ResourceDetails) => {
+                    console::truncate_str(&item.name, width, "").to_string()
+                }
+                _ => ellipsize_middle(&item.name, width),
+            };
+        }
+
+        col.max_width = width;
+    }
+
+    table
+}
+
+/// Compute the widths of the columns, that fit into `cols`. Returns the
+/// number of collapsed columns on the left and the widths of the others.
+fn allocate_widths(table: &[Col], cols: usize) -> (usize, Vec<usize>) {
+    // every column is padded with a space on both sides
+    let padding = 2;
+
+    let focused = table.iter().position(|c| c.selected).unwrap_or(0);
+    let budgets: Vec<ColBudget> = table.iter().map(|c| ColBudget::of(c.col_type)).collect();
+    let desired: Vec<usize> = table
+        .iter()
+        .zip(&budgets)
+        .map(|(c, b)| cmp::min(c.max_width, b.max))
+        .collect();
+    let minimal: Vec<usize> = desired
+        .iter()
+        .zip(&budgets)
+        .map(|(d, b)| cmp::min(*d, b.min))
+        .collect();
+
+    let mut first = 0;
+    while first < focused && minimal[first..].iter().map(|m| m + padding).sum::<usize>() > cols {
+        first += 1;
+    }
+
+    let mut widths: Vec<usize> = minimal[first..].to_vec();
+    let mut remaining = cols.saturating_sub(widths.iter().map(|w| w + padding).sum());
+
+    while remaining > 0 {
+        let open: Vec<usize> = (0..widths.len())
+            .filter(|i| widths[*i] < desired[first + i])
+            .collect();
+        let total_weight: usize = open.iter().map(|i| budgets[first + i].weight).sum();
+        if open.is_empty() || total_weight == 0 {
+            break;
+        }
+
+        let mut distributed = 0;
+        for i in open {
+            let share = cmp::max(1, remaining * budgets[first + i].weight / total_weight);
+            let add = cmp::min(
+                share,
+                cmp::min(desired[first + i] - widths[i], remaining - distributed),
+            );
+
+            widths[i] += add;
+            distributed += add;
+        }
+
+        if distributed == 0 {
+            break;
+        }
+        remaining -= distributed;
+    }
+
+    (first, widths)
+}
+
+/// Header of the column with the loading, stale or timing status. Data is
+/// stale, once it is older than the TTL of the cache.
+fn column_header(state: &State, col_type: ColType, header: &str) -> String {
+    match state.loading.status(&col_type, state.cache.ttl()) {
+        Some(status) => format!("{} {}", header, status),
+        None => header.to_owned(),
+    }
+}
+
+/// Lines of the diagnostic of the error, without blank lines.
+pub fn error_lines(error: &PluginError) -> Vec<String> {
+    format!("{:?}", error.report)
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| l.to_owned())
+        .collect()
+}
+
+/// Append the CPU and memory usage as aligned columns to the labels.
+fn metrics_labels(
+    labels: &[String],
+    resources: &[Resource],
+    metrics: &BTreeMap<String, Metrics>,
+) -> Vec<String> {
+    let label_width = labels
+        .iter()
+        .map(|l| console::measure_text_width(l))
+        .max()
+        .unwrap_or(0);
+    let cpu_width = metrics
+        .values()
+        .map(|m| console::measure_text_width(&m.cpu))
+        .max()
+        .unwrap_or(0);
+
+    labels
+        .iter()
+        .zip(resources)
+        .map(|(label, resource)| {
+            let label = console::pad_str(label, label_width, console::Alignment::Left, None);
+
+            match metrics.get(&resource.name) {
+                Some(m) => format!(
+                    "{}  {}  {}",
+                    label,
+                    console::pad_str(&m.cpu, cpu_width, console::Alignment::Right, None),
+                    m.memory
+                ),
+                None => label.to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Soft-wrap the lines at `width`, prefixing continued lines with a marker.
+/// Returns the wrapped lines and the index of the first wrapped line for
+/// every original line.
+fn wrap_lines(lines: &[String], width: usize) -> (Vec<String>, Vec<usize>) {
+    let marker = "↪ ";
+    let marker_width = console::measure_text_width(marker);
+
+    let mut wrapped: Vec<String> = vec![];
+    let mut line_map: Vec<usize> = vec![];
+
+    for line in lines {
+        line_map.push(wrapped.len());
+
+        let mut chars: Vec<char> = line.chars().collect();
+        let mut line_width = width.max(1);
+        let mut prefix = "";
+
+        loop {
+            let rest = chars.split_off(cmp::min(line_width, chars.len()));
+            wrapped.push(format!("{}{}", prefix, chars.iter().collect::<String>()));
+
+            if rest.is_empty() {
+                break;
+            }
+
+            chars = rest;
+            prefix = marker;
+            line_width = width.saturating_sub(marker_width).max(1);
+        }
+    }
+
+    (wrapped, line_map)
+}
+
+fn ellipsize_middle(text: &str, width: usize) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    if chars.len() <= width {
+        return text.to_owned();
+    }
+
+    if width <= 1 {
+        return "…".chars().take(width).collect();
+    }
+
+    let keep = width - 1;
+    let left = keep.div_ceil(2);
+    let right = keep / 2;
+
+    format!(
+        "{}…{}",
+        chars[..left].iter().collect::<String>(),
+        chars[chars.len() - right..].iter().collect::<String>()
+    )
 }
 
 fn hex_to_rgb(s: &str) -> Result<Vec<u8>> {
@@ -371,6 +1084,33 @@ fn parse_color(color: &str) -> Result<Color> {
     .into())
 }
 
+/// Parse the per-context colors in the format `<pattern>=<color>`, separated
+/// by spaces, e.g. `prod-*=red staging=yellow`.
+fn parse_context_colors(context_colors: &str) -> Result<Vec<(String, Style)>> {
+    context_colors
+        .split_whitespace()
+        .map(|entry| {
+            let (pattern, color) = match entry.split_once('=') {
+                Some(pair) => pair,
+                None => {
+                    return Err(InvalidColor {
+                        src: NamedSource::new(
+                            "layout.kdl",
+                            format!("context color \"{}\" must be <context>=<color>", entry),
+                        ),
+                        bad_bit: (0, entry.len()).into(),
+                    }
+                    .into());
+                }
+            };
+
+            let style = Style::new().bg_color(Some(parse_color(color)?)).bold();
+
+            Ok((pattern.to_owned(), style))
+        })
+        .collect()
+}
+
 fn color_by_name(color: &str) -> Option<AnsiColor> {
     match color {
         "black" => Some(AnsiColor::Black),
@@ -0,0 +1,155 @@
+use miette::Result;
+
+use crate::config::parse_bool;
+use crate::custom::CustomOutput;
+use crate::kubernetes::ResourceRef;
+
+/// Actions, that change resources in the cluster. They are blocked in
+/// read-only mode and need a typed confirmation in protected contexts.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MutatingAction {
+    Apply {
+        path: String,
+        file: String,
+    },
+    Custom {
+        name: String,
+        args: Vec<String>,
+        output: CustomOutput,
+        resource_ref: ResourceRef,
+    },
+    Cordon {
+        node: String,
+    },
+    Uncordon {
+        node: String,
+    },
+    Drain {
+        node: String,
+        options: Vec<String>,
+    },
+}
+
+impl MutatingAction {
+    pub fn description(&self) -> String {
+        match self {
+            Self::Apply { .. } => "apply the changes".to_owned(),
+            Self::Custom { name, .. } => format!("run \"{}\"", name),
+            Self::Cordon { node } => format!("cordon node {}", node),
+            Self::Uncordon { node } => format!("uncordon node {}", node),
+            Self::Drain { node, .. } => format!("drain node {}", node),
+        }
+    }
+
+    /// Whether the action must be confirmed in every context, as it affects
+    /// a whole node or applies edited changes after their diff was shown.
+    pub fn needs_confirmation(&self) -> bool {
+        matches!(
+            self,
+            Self::Apply { .. } | Self::Cordon { .. } | Self::Uncordon { .. } | Self::Drain { .. }
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Safety {
+    pub read_only: bool,
+    protected_contexts: Vec<String>,
+}
+
+impl Safety {
+    pub fn new(read_only: Option<&str>, protected_contexts: Option<&str>) -> Result<Self> {
+        let read_only = parse_bool("read_only", read_only, false)?;
+
+        let protected_contexts = protected_contexts
+            .unwrap_or_default()
+            .split([' ', ','])
+            .filter(|p| !p.is_empty())
+            .map(|p| p.to_owned())
+            .collect();
+
+        Ok(Self {
+            read_only,
+            protected_contexts,
+        })
+    }
+
+    pub fn has_protected_contexts(&self) -> bool {
+        !self.protected_contexts.is_empty()
+    }
+
+    pub fn is_protected(&self, context: &str) -> bool {
+        self.protected_contexts
+            .iter()
+            .any(|pattern| glob_match(pattern, context))
+    }
+}
+
+/// Match `text` against a glob `pattern`, which supports `*` for any
+/// sequence of characters and `?` for a single character.
+pub(crate) fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((bp, bt)) => {
+                    p = bp + 1;
+                    t = bt + 1;
+                    backtrack = Some((bp, bt + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_match_literal() {
+        assert!(glob_match("prod", "prod"));
+        assert!(!glob_match("prod", "production"));
+        assert!(!glob_match("prod", "pro"));
+    }
+
+    #[test]
+    fn glob_match_wildcards() {
+        assert!(glob_match("prod-*", "prod-eu"));
+        assert!(glob_match("prod-*", "prod-"));
+        assert!(glob_match("*-prod", "eu-prod"));
+        assert!(glob_match("*prod*", "eu-prod-1"));
+        assert!(glob_match("*", ""));
+        assert!(!glob_match("prod-*", "staging-eu"));
+    }
+
+    #[test]
+    fn glob_match_single_character() {
+        assert!(glob_match("prod-?", "prod-1"));
+        assert!(!glob_match("prod-?", "prod-12"));
+        assert!(!glob_match("prod-?", "prod-"));
+    }
+
+    #[test]
+    fn glob_match_backtracks() {
+        assert!(glob_match("*-eu-*", "prod-eu-west-eu-1"));
+        assert!(glob_match("a*b*c", "aXbYbZc"));
+        assert!(!glob_match("a*b*c", "aXbYbZ"));
+    }
+}
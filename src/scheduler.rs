@@ -0,0 +1,268 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::BTreeMap;
+
+use crate::kubernetes;
+
+// milliseconds a query waits, when another one of its kind was requested
+// shortly before, e.g. while scrolling through the namespaces
+const DEBOUNCE_MS: i64 = 150;
+
+/// Queries for the lists of the columns, that are dispatched by the
+/// scheduler.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Query {
+    Namespaces,
+    ResourceTypes {
+        namespace: String,
+    },
+    Resources {
+        namespace: String,
+        resource_type: String,
+    },
+}
+
+impl Query {
+    pub fn command(&self) -> &'static str {
+        match self {
+            Self::Namespaces => "query_namespaces",
+            Self::ResourceTypes { .. } => "query_resource_types",
+            Self::Resources { .. } => "query_resources",
+        }
+    }
+
+    /// The query, that produced the result with the context. None for the
+    /// commands, that are not dispatched by the scheduler.
+    pub fn from_context(context: &BTreeMap<String, String>) -> Option<Self> {
+        let field = |name: &str| context.get(name).cloned().unwrap_or_default();
+
+        match context.get("command")?.as_str() {
+            "query_namespaces" => Some(Self::Namespaces),
+            "query_resource_types" => Some(Self::ResourceTypes {
+                namespace: field("namespace"),
+            }),
+            "query_resources" => Some(Self::Resources {
+                namespace: field("namespace"),
+                resource_type: field("resource_type"),
+            }),
+            _ => None,
+        }
+    }
+
+    pub fn run(&self, kube_context: &Option<&str>) {
+        match self {
+            Self::Namespaces => kubernetes::query_namespaces(*kube_context),
+            Self::ResourceTypes { namespace } => {
+                kubernetes::query_resource_types(kube_context, namespace)
+            }
+            Self::Resources {
+                namespace,
+                resource_type,
+            } => kubernetes::query_resources(kube_context, namespace, resource_type),
+        }
+    }
+}
+
+/// Dispatches the queries of the columns. Requests for a query, that is
+/// already in flight or waiting, are coalesced and a query requested
+/// shortly after another one of its kind waits, until the requests settle.
+///
+/// Queries for the details column carry a request id instead, such that
+/// only the result of the latest one is shown.
+#[derive(Debug, Default)]
+pub struct Scheduler {
+    // latest dispatched query per command, that did not return yet
+    in_flight: BTreeMap<&'static str, Query>,
+
+    // queries waiting for the debounce delay, with the time they are due
+    pending: BTreeMap<&'static str, (Query, DateTime<Utc>)>,
+
+    // time of the last new request per command
+    last_request: BTreeMap<&'static str, DateTime<Utc>>,
+
+    // id of the latest query for the details column
+    details_request: u64,
+}
+
+impl Scheduler {
+    pub fn request(&mut self, query: Query, now: DateTime<Utc>) {
+        let command = query.command();
+
+        if self.in_flight.get(command) == Some(&query) {
+            self.pending.remove(command);
+            return;
+        }
+
+        if self.pending.get(command).is_some_and(|(q, _)| *q == query) {
+            return;
+        }
+
+        let debounce = self
+            .last_request
+            .get(command)
+            .is_some_and(|last| now - *last < Duration::milliseconds(DEBOUNCE_MS));
+        let due = if debounce {
+            now + Duration::milliseconds(DEBOUNCE_MS)
+        } else {
+            now
+        };
+
+        self.last_request.insert(command, now);
+        self.pending.insert(command, (query, due));
+    }
+
+    /// Take the queries, that are due, and track them as in flight.
+    pub fn due(&mut self, now: DateTime<Utc>) -> Vec<Query> {
+        let commands: Vec<&'static str> = self
+            .pending
+            .iter()
+            .filter(|(_, (_, due))| *due <= now)
+            .map(|(command, _)| *command)
+            .collect();
+
+        let mut due = vec![];
+        for command in commands {
+            if let Some((query, _)) = self.pending.remove(command) {
+                self.in_flight.insert(command, query.clone());
+                due.push(query);
+            }
+        }
+
+        due
+    }
+
+    /// Seconds until the next waiting query is due.
+    pub fn next_due(&self, now: DateTime<Utc>) -> Option<f64> {
+        self.pending
+            .values()
+            .map(|(_, due)| (*due - now).num_milliseconds().max(0) as f64 / 1000.0)
+            .reduce(f64::min)
+    }
+
+    /// Forget the queries in flight and waiting, e.g. after switching the
+    /// context. Request ids keep counting, so late results stay obsolete.
+    pub fn clear(&mut self) {
+        self.in_flight.clear();
+        self.pending.clear();
+        self.last_request.clear();
+    }
+
+    /// Id for a new query of the details column, that supersedes all
+    /// previous ones.
+    pub fn details_request(&mut self) -> u64 {
+        self.details_request += 1;
+        self.details_request
+    }
+
+    /// Track a query, that was dispatched outside of the scheduler, e.g.
+    /// when it is retried.
+    pub fn track(&mut self, query: Query) {
+        self.in_flight.insert(query.command(), query);
+    }
+
+    /// Whether the result with the context belongs to the latest query of
+    /// its kind. Results of superseded queries are obsolete.
+    pub fn finish(&mut self, context: &BTreeMap<String, String>) -> bool {
+        if let Some(request_id) = context.get("request_id") {
+            return *request_id == self.details_request.to_string();
+        }
+
+        let query = match Query::from_context(context) {
+            Some(query) => query,
+            None => return true,
+        };
+
+        if self.in_flight.get(query.command()) != Some(&query) {
+            return false;
+        }
+
+        self.in_flight.remove(query.command());
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resource_types(namespace: &str) -> Query {
+        Query::ResourceTypes {
+            namespace: namespace.to_owned(),
+        }
+    }
+
+    fn context(query: &Query) -> BTreeMap<String, String> {
+        let mut context = BTreeMap::from([("command".to_owned(), query.command().to_owned())]);
+        if let Query::ResourceTypes { namespace } = query {
+            context.insert("namespace".to_owned(), namespace.clone());
+        }
+
+        context
+    }
+
+    #[test]
+    fn first_request_is_due_right_away() {
+        let mut scheduler = Scheduler::default();
+        let now = Utc::now();
+
+        scheduler.request(resource_types("a"), now);
+
+        assert_eq!(scheduler.due(now), vec![resource_types("a")]);
+        assert_eq!(scheduler.next_due(now), None);
+    }
+
+    #[test]
+    fn quick_requests_are_debounced_to_the_latest() {
+        let mut scheduler = Scheduler::default();
+        let now = Utc::now();
+
+        scheduler.request(resource_types("a"), now);
+        scheduler.due(now);
+        scheduler.request(resource_types("b"), now + Duration::milliseconds(50));
+        scheduler.request(resource_types("c"), now + Duration::milliseconds(100));
+
+        assert!(scheduler.due(now + Duration::milliseconds(100)).is_empty());
+        assert_eq!(
+            scheduler.due(now + Duration::milliseconds(250)),
+            vec![resource_types("c")]
+        );
+    }
+
+    #[test]
+    fn requests_for_queries_in_flight_are_coalesced() {
+        let mut scheduler = Scheduler::default();
+        let now = Utc::now();
+
+        scheduler.request(resource_types("a"), now);
+        scheduler.due(now);
+        scheduler.request(resource_types("a"), now + Duration::seconds(1));
+
+        assert!(scheduler.due(now + Duration::seconds(1)).is_empty());
+    }
+
+    #[test]
+    fn results_of_superseded_queries_are_obsolete() {
+        let mut scheduler = Scheduler::default();
+        let now = Utc::now();
+
+        scheduler.request(resource_types("a"), now);
+        scheduler.due(now);
+        scheduler.request(resource_types("b"), now + Duration::seconds(1));
+        scheduler.due(now + Duration::seconds(1));
+
+        assert!(!scheduler.finish(&context(&resource_types("a"))));
+        assert!(scheduler.finish(&context(&resource_types("b"))));
+    }
+
+    #[test]
+    fn only_the_latest_details_request_is_current() {
+        let mut scheduler = Scheduler::default();
+
+        let first = scheduler.details_request();
+        let second = scheduler.details_request();
+
+        let request = |id: u64| BTreeMap::from([("request_id".to_owned(), id.to_string())]);
+        assert!(!scheduler.finish(&request(first)));
+        assert!(scheduler.finish(&request(second)));
+    }
+}
@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::kubernetes::{ResourceSort, Selection};
+use crate::render::{ColType, View};
+
+// folder in the host folder, where the favorites and view states are saved,
+// unless it is configured with `state_dir`
+const DEFAULT_STATE_DIR: &str = ".zjk8s";
+
+/// The folder for the favorites and view states. The data folder of the
+/// plugin is removed, when the plugin is unloaded, so they are kept in the
+/// host folder instead, which is the folder zellij was started in.
+pub fn state_dir(configured: Option<&str>) -> PathBuf {
+    let dir = configured.unwrap_or(DEFAULT_STATE_DIR);
+
+    PathBuf::from("/host").join(dir.trim_start_matches('/'))
+}
+
+/// The state of the user interface, that is saved per plugin instance and
+/// restored when the plugin is loaded again.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ViewState {
+    pub context: Option<String>,
+    #[serde(flatten)]
+    pub selection: Selection,
+    pub col_type: ColType,
+    pub view: View,
+    pub resource_sort: ResourceSort,
+    pub wrap_resource_details: bool,
+}
+
+impl ViewState {
+    /// Read the state of the instance from the state folder. Nothing is
+    /// restored, when the instance was never saved before.
+    pub fn load(dir: &Path, instance: &str) -> Result<Option<Self>, String> {
+        let content = match std::fs::read_to_string(state_file(dir, instance)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Cannot read the last view state: {}", e)),
+        };
+
+        serde_json::from_str(&content)
+            .map(Some)
+            .map_err(|e| format!("Cannot parse the last view state: {}", e))
+    }
+
+    pub fn save(&self, dir: &Path, instance: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Cannot serialize the view state: {}", e))?;
+
+        std::fs::create_dir_all(dir)
+            .and_then(|_| std::fs::write(state_file(dir, instance), content))
+            .map_err(|e| format!("Cannot save the view state: {}", e))
+    }
+}
+
+fn state_file(dir: &Path, instance: &str) -> PathBuf {
+    let name: String = instance
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+
+    dir.join(format!("state-{}.json", name))
+}
@@ -0,0 +1,311 @@
+use itertools::Itertools;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::kubernetes::Resource;
+
+/// Build the tree of a workload from the items of
+/// `kubectl get <workloads>,pods,services,persistentvolumeclaims --output json`.
+///
+/// Starting at the topmost owner of the resource, all objects owned by it are
+/// listed recursively, with the ConfigMaps, Secrets and PVCs mounted by the
+/// pods. Services selecting any of the pods are listed below the tree.
+pub fn owner_tree(list: &Value, kind: &str, name: &str) -> Vec<String> {
+    let items: Vec<&Value> = list["items"].as_array().into_iter().flatten().collect();
+
+    let mut root = match items.iter().position(|i| {
+        i["kind"].as_str() == Some(kind) && i["metadata"]["name"].as_str() == Some(name)
+    }) {
+        Some(root) => root,
+        None => return vec![format!("{}/{} not found", kind, name)],
+    };
+
+    let owners = Owners::new(&items);
+
+    // walk up the owner references to the topmost owner, cyclic references
+    // stop at the first item seen twice
+    let mut visited = BTreeSet::from([root]);
+    while let Some(owner) = owners.owner[root].filter(|o| visited.insert(*o)) {
+        root = owner;
+    }
+
+    let mut lines = vec![label(items[root])];
+    let mut pods: Vec<&Value> = vec![];
+    let mut visited = BTreeSet::from([root]);
+    add_children(
+        &items,
+        &owners,
+        root,
+        "",
+        &mut lines,
+        &mut pods,
+        &mut visited,
+    );
+
+    let root = items[root];
+
+    if root["kind"].as_str() == Some("Pod") {
+        pods.push(root);
+        add_mounts(&items, root, "", &mut lines);
+    }
+
+    let services: Vec<&Value> = items
+        .iter()
+        .filter(|i| i["kind"].as_str() == Some("Service"))
+        .filter(|s| pods.iter().any(|p| selects(s, p)))
+        .copied()
+        .collect();
+
+    if !services.is_empty() {
+        lines.push("".to_owned());
+        lines.push("Services".to_owned());
+        for service in services {
+            lines.push(format!("  {}", label(service)));
+        }
+    }
+
+    lines
+}
+
+/// Owner relations between the items, referenced by their index in the
+/// list. They are resolved once, such that the tree is walked without
+/// searching the items again.
+struct Owners {
+    // first listed owner per item
+    owner: Vec<Option<usize>>,
+
+    // owned items per owner in the order of the list
+    children: BTreeMap<usize, Vec<usize>>,
+}
+
+impl Owners {
+    fn new(items: &[&Value]) -> Self {
+        let mut indices: BTreeMap<&str, usize> = BTreeMap::new();
+        for (index, item) in items.iter().enumerate() {
+            if let Some(uid) = item["metadata"]["uid"].as_str() {
+                indices.entry(uid).or_insert(index);
+            }
+        }
+
+        let owner: Vec<Option<usize>> = items
+            .iter()
+            .map(|item| {
+                item["metadata"]["ownerReferences"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|o| o["uid"].as_str())
+                    .filter_map(|uid| indices.get(uid).copied())
+                    .min()
+            })
+            .collect();
+
+        let mut children: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+        for (index, owner) in owner.iter().enumerate() {
+            if let Some(owner) = owner {
+                children.entry(*owner).or_default().push(index);
+            }
+        }
+
+        Self { owner, children }
+    }
+}
+
+fn add_children<'a>(
+    items: &[&'a Value],
+    owners: &Owners,
+    parent: usize,
+    prefix: &str,
+    lines: &mut Vec<String>,
+    pods: &mut Vec<&'a Value>,
+    visited: &mut BTreeSet<usize>,
+) {
+    // items, that are already in the tree, are skipped on cyclic references
+    let children: Vec<usize> = owners
+        .children
+        .get(&parent)
+        .into_iter()
+        .flatten()
+        .filter(|child| visited.insert(**child))
+        .copied()
+        .collect();
+
+    for (index, child_index) in children.iter().copied().enumerate() {
+        let child = items[child_index];
+        let last = index == children.len() - 1;
+        let (branch, indent) = if last {
+            ("└─ ", "   ")
+        } else {
+            ("├─ ", "│  ")
+        };
+
+        lines.push(format!("{}{}{}", prefix, branch, label(child)));
+
+        let child_prefix = format!("{}{}", prefix, indent);
+        if child["kind"].as_str() == Some("Pod") {
+            pods.push(child);
+            add_mounts(items, child, &child_prefix, lines);
+        }
+
+        add_children(
+            items,
+            owners,
+            child_index,
+            &child_prefix,
+            lines,
+            pods,
+            visited,
+        );
+    }
+}
+
+/// Add the ConfigMaps, Secrets and PVCs used by the pod as leaves.
+fn add_mounts(items: &[&Value], pod: &Value, prefix: &str, lines: &mut Vec<String>) {
+    let mut mounts: Vec<String> = vec![];
+
+    for volume in pod["spec"]["volumes"].as_array().into_iter().flatten() {
+        if let Some(name) = volume["configMap"]["name"].as_str() {
+            mounts.push(format!("ConfigMap/{}", name));
+        }
+        if let Some(name) = volume["secret"]["secretName"].as_str() {
+            mounts.push(format!("Secret/{}", name));
+        }
+        if let Some(name) = volume["persistentVolumeClaim"]["claimName"].as_str() {
+            let pvc = items.iter().find(|i| {
+                i["kind"].as_str() == Some("PersistentVolumeClaim")
+                    && i["metadata"]["name"].as_str() == Some(name)
+            });
+
+            mounts.push(match pvc {
+                Some(pvc) => label(pvc),
+                None => format!("PersistentVolumeClaim/{}", name),
+            });
+        }
+    }
+
+    for container in pod["spec"]["containers"].as_array().into_iter().flatten() {
+        for env_from in container["envFrom"].as_array().into_iter().flatten() {
+            if let Some(name) = env_from["configMapRef"]["name"].as_str() {
+                mounts.push(format!("ConfigMap/{}", name));
+            }
+            if let Some(name) = env_from["secretRef"]["name"].as_str() {
+                mounts.push(format!("Secret/{}", name));
+            }
+        }
+    }
+
+    let mounts: Vec<String> = mounts.into_iter().unique().collect();
+
+    for (index, mount) in mounts.iter().enumerate() {
+        let branch = if index == mounts.len() - 1 {
+            "└─ "
+        } else {
+            "├─ "
+        };
+
+        lines.push(format!("{}{}{}", prefix, branch, mount));
+    }
+}
+
+pub(crate) fn selects(service: &Value, pod: &Value) -> bool {
+    let selector = match service["spec"]["selector"].as_object() {
+        Some(selector) if !selector.is_empty() => selector,
+        _ => return false,
+    };
+
+    selector
+        .iter()
+        .all(|(key, value)| pod["metadata"]["labels"][key] == *value)
+}
+
+fn label(item: &Value) -> String {
+    let resource = Resource::from_json(item);
+    let kind = item["kind"].as_str().unwrap_or_default();
+
+    match status_marker(&resource.status) {
+        Some(marker) => format!("{} {}/{} {}", marker, kind, resource.name, resource.status),
+        None => format!("{}/{}", kind, resource.name),
+    }
+}
+
+fn status_marker(status: &str) -> Option<&'static str> {
+    if status.is_empty() {
+        return None;
+    }
+
+    if let Some((ready, desired)) = status.split_once('/') {
+        return if ready == desired {
+            Some("✔")
+        } else {
+            Some("●")
+        };
+    }
+
+    match status {
+        "Running" | "Succeeded" | "Active" | "Bound" => Some("✔"),
+        "Pending" | "ContainerCreating" | "PodInitializing" => Some("●"),
+        _ => Some("✖"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn item(kind: &str, name: &str, uid: &str, owner: Option<&str>) -> Value {
+        let owner_references: Vec<Value> =
+            owner.map(|uid| json!({ "uid": uid })).into_iter().collect();
+
+        json!({
+            "kind": kind,
+            "metadata": {
+                "name": name,
+                "uid": uid,
+                "ownerReferences": owner_references,
+                "labels": { "app": "web" },
+            },
+        })
+    }
+
+    #[test]
+    fn tree_starts_at_the_topmost_owner() {
+        let list = json!({
+            "items": [
+                item("Pod", "web-1", "p1", Some("rs")),
+                item("Deployment", "web", "deploy", None),
+                item("ReplicaSet", "web-abc", "rs", Some("deploy")),
+                item("Pod", "web-2", "p2", Some("rs")),
+                { "kind": "Service", "metadata": { "name": "web" }, "spec": { "selector": { "app": "web" } } },
+            ],
+        });
+
+        assert_eq!(
+            owner_tree(&list, "Pod", "web-2"),
+            vec![
+                "Deployment/web",
+                "└─ ReplicaSet/web-abc",
+                "   ├─ Pod/web-1",
+                "   └─ Pod/web-2",
+                "",
+                "Services",
+                "  Service/web",
+            ]
+        );
+    }
+
+    #[test]
+    fn cyclic_owner_references_terminate() {
+        let list = json!({
+            "items": [
+                item("ReplicaSet", "a", "a", Some("b")),
+                item("ReplicaSet", "b", "b", Some("a")),
+            ],
+        });
+
+        assert_eq!(
+            owner_tree(&list, "ReplicaSet", "a"),
+            vec!["ReplicaSet/b", "└─ ReplicaSet/a"]
+        );
+    }
+}